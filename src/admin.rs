@@ -0,0 +1,320 @@
+//! Offline operations on the database of a bot instance, for operators who need
+//! to fix data without going through Telegram. Used by the `admin` binary:
+//!
+//! ```sh
+//! MONGO_OPTIONS=mongodb://localhost:27017 cargo run --bin admin -- groups -1001234567890
+//! ```
+//!
+//! Only MongoDB is used, so the bot may keep running, but dialogues it has in
+//! memory may refer to groups and warn types deleted here.
+
+use crate::{
+    config::{self, Config},
+    jobs::Scheduler,
+    migrations::{self, SCHEMA_VERSION},
+    settings::SettingsRepository,
+    templates::Templates,
+    warnings::{
+        OnWarnAction, Punishment, PunishmentKind, PunishmentTime, WarningGroup, WarningInfo,
+        WarnsRepository,
+    },
+};
+use anyhow::Context;
+use chrono::{TimeZone, Utc};
+use mongodb::{bson::oid::ObjectId, Database};
+use std::{fmt::Write, path::PathBuf, str::FromStr};
+
+pub const USAGE: &str = "\
+usage: admin <command> [arguments]
+
+commands:
+    groups <chat>                      list warn groups
+    create-group <chat> <name> <max points> <ban|mute> <seconds|forever>
+    delete-group <chat> <name>         the group must have no warn types
+    types <chat>                       list warn types
+    create-type <chat> <trigger> <points> <group> [delete_message|nothing]
+    delete-type <chat> <trigger>       active warns of the type are archived
+    warns <chat> <user>                show active warns and punishment of a user
+    clear-warns <chat> <user> [group]  archive active warns of a user
+    migrate                            migrate documents and create indexes
+    export <chat>                      print the configuration of the chat as TOML
+    import <config file>               sync a configuration file like at startup
+
+Groups restricting users are created with `import`, as they need permissions.";
+
+enum Command {
+    Groups { chat_id: i64 },
+    CreateGroup { chat_id: i64, name: String, max_points: u64, punishment: Punishment },
+    DeleteGroup { chat_id: i64, name: String },
+    Types { chat_id: i64 },
+    CreateType { chat_id: i64, trigger: String, points: u64, group: String, on_warn: OnWarnAction },
+    DeleteType { chat_id: i64, trigger: String },
+    Warns { chat_id: i64, user_id: i64 },
+    ClearWarns { chat_id: i64, user_id: i64, group: Option<String> },
+    Migrate,
+    Export { chat_id: i64 },
+    Import { path: PathBuf },
+}
+
+/// Runs the command given in the arguments, without the name of the binary,
+/// and returns what to print. `legacy_chat` is used by `migrate`, see
+/// [`DatabaseConfig`](crate::DatabaseConfig).
+pub async fn run(
+    db: &Database,
+    legacy_chat: Option<i64>,
+    args: &[String],
+) -> anyhow::Result<String> {
+    let command = parse(args).with_context(|| format!("invalid arguments\n\n{}", USAGE))?;
+    let repo = WarnsRepository::new(db);
+    let settings_repo = SettingsRepository::new(db);
+    let mut out = String::new();
+    match command {
+        Command::Groups { chat_id } => {
+            for group in repo.get_warn_groups(chat_id).await? {
+                writeln!(
+                    out,
+                    "{}: {} points, {}",
+                    group.name,
+                    group.max_points,
+                    describe_punishment(&group.punishment)
+                )?;
+            }
+        }
+        Command::CreateGroup { chat_id, name, max_points, punishment } => {
+            if max_points == 0 {
+                anyhow::bail!("max points must be positive");
+            }
+            if repo.get_warn_group(chat_id, &name).await?.is_some() {
+                anyhow::bail!("group `{}` already exists", name);
+            }
+            let group = WarningGroup {
+                id: ObjectId::new(),
+                chat_id,
+                name,
+                max_points,
+                punishment,
+                templates: Templates::new(),
+                schema_version: SCHEMA_VERSION,
+            };
+            repo.upsert_group(group.clone()).await?;
+            writeln!(out, "Created group `{}`", group.name)?;
+        }
+        Command::DeleteGroup { chat_id, name } => {
+            let group = find_group(&repo, chat_id, &name).await?;
+            let triggers: Vec<_> = repo
+                .get_warn_types(chat_id)
+                .await?
+                .into_iter()
+                .filter(|ty| ty.group_id == group.id)
+                .map(|ty| ty.trigger)
+                .collect();
+            if !triggers.is_empty() {
+                anyhow::bail!(
+                    "group `{}` has warn types, delete them first: {}",
+                    name,
+                    triggers.join(", ")
+                );
+            }
+            repo.delete_group(&group).await?;
+            writeln!(out, "Deleted group `{}`", name)?;
+        }
+        Command::Types { chat_id } => {
+            let groups = repo.get_warn_groups(chat_id).await?;
+            for ty in repo.get_warn_types(chat_id).await? {
+                let group = groups
+                    .iter()
+                    .find(|g| g.id == ty.group_id)
+                    .map_or("<missing group>", |g| g.name.as_str());
+                let on_warn = match ty.on_warn {
+                    OnWarnAction::DeleteMessage => ", deletes the message",
+                    OnWarnAction::Nothing => "",
+                };
+                writeln!(out, "{}: {} points in `{}`{}", ty.trigger, ty.points, group, on_warn)?;
+            }
+        }
+        Command::CreateType { chat_id, trigger, points, group, on_warn } => {
+            if trigger.split_whitespace().count() != 1 {
+                anyhow::bail!("trigger must be a single word");
+            }
+            if points == 0 {
+                anyhow::bail!("points must be positive");
+            }
+            let group = find_group(&repo, chat_id, &group).await?;
+            let ty = WarningInfo {
+                id: ObjectId::new(),
+                chat_id,
+                trigger,
+                points,
+                group_id: group.id,
+                on_warn,
+                templates: Templates::new(),
+                schema_version: SCHEMA_VERSION,
+            };
+            if !repo.insert_warn_type(ty.clone()).await? {
+                anyhow::bail!("warn type `{}` already exists", ty.trigger);
+            }
+            writeln!(out, "Created warn type `{}`", ty.trigger)?;
+        }
+        Command::DeleteType { chat_id, trigger } => {
+            if !repo.delete_warn_type(chat_id, &trigger).await? {
+                anyhow::bail!("there is no warn type `{}`", trigger);
+            }
+            writeln!(out, "Deleted warn type `{}`", trigger)?;
+        }
+        Command::Warns { chat_id, user_id } => {
+            let warns = repo.get_actual_warns(user_id).await?;
+            for group in repo.get_warn_groups(chat_id).await? {
+                let warns: Vec<_> = warns.iter().filter(|w| w.group_id == group.id).collect();
+                if warns.is_empty() {
+                    continue;
+                }
+                let points: u64 = warns.iter().map(|w| w.points).sum();
+                writeln!(out, "{}: {}/{} points", group.name, points, group.max_points)?;
+                for warn in warns {
+                    writeln!(out, "  {} ({})", warn.trigger, warn.points)?;
+                }
+            }
+            let punishment = repo.get_active_punishment(chat_id, user_id).await?;
+            let now = Utc::now().timestamp();
+            if let Some(punishment) = punishment.filter(|p| p.until > now) {
+                writeln!(
+                    out,
+                    "Punished for `{}` until {}",
+                    punishment.group,
+                    Utc.timestamp(punishment.until, 0).format("%Y-%m-%d %H:%M UTC")
+                )?;
+            }
+            if repo.is_immune(chat_id, user_id).await? {
+                writeln!(out, "Protected from warns")?;
+            }
+            if out.is_empty() {
+                writeln!(out, "No active warns")?;
+            }
+        }
+        Command::ClearWarns { chat_id, user_id, group } => {
+            let groups = match group {
+                Some(name) => vec![find_group(&repo, chat_id, &name).await?],
+                None => repo.get_warn_groups(chat_id).await?,
+            };
+            for group in groups {
+                repo.remove_actual_warns(user_id, &group).await?;
+                writeln!(out, "Cleared warns in `{}`", group.name)?;
+            }
+        }
+        Command::Migrate => {
+            migrations::run(db, legacy_chat).await.context("cannot migrate the database")?;
+            repo.ensure_indexes().await.context("cannot create indexes")?;
+            settings_repo.ensure_indexes().await.context("cannot create indexes")?;
+            Scheduler::new(db).ensure_indexes().await.context("cannot create indexes")?;
+            writeln!(out, "The database is up to date")?;
+        }
+        Command::Export { chat_id } => {
+            let chat = config::export_chat(chat_id, &repo, &settings_repo).await?;
+            out = toml::to_string_pretty(&Config { chats: vec![chat] })?;
+        }
+        Command::Import { path } => {
+            let config = Config::load(&path)?;
+            let report = config::sync(&config, &repo, &settings_repo)
+                .await
+                .context("cannot sync the configuration")?;
+            writeln!(out, "Synced the configuration: {}", report)?;
+        }
+    }
+    Ok(out)
+}
+
+fn parse(args: &[String]) -> anyhow::Result<Command> {
+    let (name, args) = args.split_first().ok_or_else(|| anyhow::anyhow!("no command"))?;
+    let mut args = Args(args.iter());
+    let command = match name.as_str() {
+        "groups" => Command::Groups { chat_id: args.next("chat")? },
+        "create-group" => Command::CreateGroup {
+            chat_id: args.next("chat")?,
+            name: args.next("name")?,
+            max_points: args.next("max points")?,
+            punishment: {
+                let kind = match args.next::<String>("punishment")?.as_str() {
+                    "ban" => PunishmentKind::Ban,
+                    "mute" => PunishmentKind::Mute,
+                    other => anyhow::bail!("unknown punishment `{}`", other),
+                };
+                let time = match args.next::<String>("time")?.as_str() {
+                    "forever" => PunishmentTime::Forever,
+                    secs => PunishmentTime::Time(
+                        secs.parse().map_err(|_| anyhow::anyhow!("invalid time `{}`", secs))?,
+                    ),
+                };
+                Punishment { time, kind }
+            },
+        },
+        "delete-group" => {
+            Command::DeleteGroup { chat_id: args.next("chat")?, name: args.next("name")? }
+        }
+        "types" => Command::Types { chat_id: args.next("chat")? },
+        "create-type" => Command::CreateType {
+            chat_id: args.next("chat")?,
+            trigger: args.next("trigger")?,
+            points: args.next("points")?,
+            group: args.next("group")?,
+            on_warn: match args.optional::<String>("on warn")?.as_deref() {
+                Some("delete_message") => OnWarnAction::DeleteMessage,
+                Some("nothing") | None => OnWarnAction::Nothing,
+                Some(other) => anyhow::bail!("unknown on warn action `{}`", other),
+            },
+        },
+        "delete-type" => {
+            Command::DeleteType { chat_id: args.next("chat")?, trigger: args.next("trigger")? }
+        }
+        "warns" => Command::Warns { chat_id: args.next("chat")?, user_id: args.next("user")? },
+        "clear-warns" => Command::ClearWarns {
+            chat_id: args.next("chat")?,
+            user_id: args.next("user")?,
+            group: args.optional("group")?,
+        },
+        "migrate" => Command::Migrate,
+        "export" => Command::Export { chat_id: args.next("chat")? },
+        "import" => Command::Import { path: args.next("config file")? },
+        other => anyhow::bail!("unknown command `{}`", other),
+    };
+    if let Some(arg) = args.0.next() {
+        anyhow::bail!("unexpected argument `{}`", arg);
+    }
+    Ok(command)
+}
+
+struct Args<'a>(std::slice::Iter<'a, String>);
+
+impl Args<'_> {
+    fn next<T: FromStr>(&mut self, name: &str) -> anyhow::Result<T> {
+        self.optional(name)?.ok_or_else(|| anyhow::anyhow!("missing {}", name))
+    }
+
+    fn optional<T: FromStr>(&mut self, name: &str) -> anyhow::Result<Option<T>> {
+        self.0
+            .next()
+            .map(|arg| arg.parse().map_err(|_| anyhow::anyhow!("invalid {} `{}`", name, arg)))
+            .transpose()
+    }
+}
+
+async fn find_group(
+    repo: &WarnsRepository,
+    chat_id: i64,
+    name: &str,
+) -> anyhow::Result<WarningGroup> {
+    repo.get_warn_group(chat_id, name)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("there is no group `{}` in chat {}", name, chat_id))
+}
+
+fn describe_punishment(punishment: &Punishment) -> String {
+    let kind = match punishment.kind {
+        PunishmentKind::Ban => "ban",
+        PunishmentKind::Mute => "mute",
+        PunishmentKind::Restrict(_) => "restrict",
+    };
+    match punishment.time {
+        PunishmentTime::Time(secs) => format!("{} for {} seconds", kind, secs),
+        PunishmentTime::Forever => format!("{} forever", kind),
+    }
+}
@@ -0,0 +1,145 @@
+//! Settings of a bot instance, read from environment variables. Several
+//! instances, e.g. a staging one, can share a MongoDB server by using
+//! different databases.
+
+use std::{env, net::SocketAddr, path::PathBuf};
+
+/// Token of the bot.
+pub const TOKEN_ENV: &str = "TELOXIDE_TOKEN";
+/// Connection string of the MongoDB server.
+pub const MONGO_OPTIONS_ENV: &str = "MONGO_OPTIONS";
+/// Name of the database, `database` if not set.
+pub const DATABASE_ENV: &str = "AUTOWARN_DATABASE";
+/// The chat warns, warn types and groups belong to if they were stored before
+/// the bot kept them per chat. Needed only to migrate such databases.
+pub const LEGACY_CHAT_ENV: &str = "AUTOWARN_LEGACY_CHAT";
+/// Path of the configuration of chats, see [`crate::config`].
+pub const CHATS_CONFIG_ENV: &str = "AUTOWARN_CONFIG";
+/// Comma-separated IDs of users who get details of errors in private messages.
+pub const OPERATORS_ENV: &str = "AUTOWARN_OPERATORS";
+/// Path of the file to record updates and Bot API calls to, see
+/// [`crate::recording`]. Nothing is recorded if not set.
+pub const RECORD_ENV: &str = "AUTOWARN_RECORD";
+/// The address of the status server with metrics, not started if not set.
+pub const STATUS_ADDR_ENV: &str = "AUTOWARN_STATUS_ADDR";
+/// Public URL of the webhook. If set, updates are received with the webhook
+/// instead of long polling, see [`crate::webhook`].
+pub const WEBHOOK_URL_ENV: &str = "AUTOWARN_WEBHOOK_URL";
+/// The secret part of the webhook path, required in the webhook mode.
+pub const WEBHOOK_SECRET_ENV: &str = "AUTOWARN_WEBHOOK_SECRET";
+/// The address the webhook listener binds to, `127.0.0.1:8080` if not set.
+pub const WEBHOOK_ADDR_ENV: &str = "AUTOWARN_WEBHOOK_ADDR";
+
+const DEFAULT_DATABASE: &str = "database";
+const DEFAULT_WEBHOOK_ADDR: &str = "127.0.0.1:8080";
+/// Characters allowed in the secret, so it is safe in the URL path.
+const SECRET_CHARS: &str = "A-Z, a-z, 0-9, _ and -";
+
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub token: String,
+    pub mongo_options: String,
+    pub database: String,
+    pub legacy_chat: Option<i64>,
+    pub chats_config: Option<PathBuf>,
+    pub operators: Vec<i64>,
+    pub status_addr: Option<SocketAddr>,
+    pub record: Option<PathBuf>,
+    /// `None` if updates are received with long polling.
+    pub webhook: Option<WebhookConfig>,
+}
+
+/// Where the data of a bot instance is stored, without the rest of
+/// [`AppConfig`], for tools which do not talk to Telegram.
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub mongo_options: String,
+    pub database: String,
+    pub legacy_chat: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub secret: String,
+    pub addr: SocketAddr,
+}
+
+impl AppConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let token = required(TOKEN_ENV)?;
+        let DatabaseConfig { mongo_options, database, legacy_chat } = DatabaseConfig::from_env()?;
+        Ok(Self {
+            token,
+            mongo_options,
+            database,
+            legacy_chat,
+            chats_config: env::var_os(CHATS_CONFIG_ENV).map(PathBuf::from),
+            operators: parse_operators(&optional(OPERATORS_ENV)?.unwrap_or_default())?,
+            status_addr: optional(STATUS_ADDR_ENV)?
+                .map(|addr| parse_addr(STATUS_ADDR_ENV, &addr))
+                .transpose()?,
+            record: env::var_os(RECORD_ENV).map(PathBuf::from),
+            webhook: WebhookConfig::from_env()?,
+        })
+    }
+}
+
+impl DatabaseConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            mongo_options: required(MONGO_OPTIONS_ENV)?,
+            database: optional(DATABASE_ENV)?.unwrap_or_else(|| DEFAULT_DATABASE.to_string()),
+            legacy_chat: optional(LEGACY_CHAT_ENV)?
+                .map(|id| {
+                    id.parse().map_err(|_| {
+                        anyhow::anyhow!("invalid chat ID in {}: {}", LEGACY_CHAT_ENV, id)
+                    })
+                })
+                .transpose()?,
+        })
+    }
+}
+
+impl WebhookConfig {
+    fn from_env() -> anyhow::Result<Option<Self>> {
+        let url = match optional(WEBHOOK_URL_ENV)? {
+            Some(url) => url,
+            None => return Ok(None),
+        };
+        let secret = required(WEBHOOK_SECRET_ENV)?;
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        if secret.is_empty() || !secret.chars().all(valid) {
+            anyhow::bail!("{} may contain only {}", WEBHOOK_SECRET_ENV, SECRET_CHARS);
+        }
+        let addr = optional(WEBHOOK_ADDR_ENV)?.unwrap_or_else(|| DEFAULT_WEBHOOK_ADDR.to_string());
+        let addr = parse_addr(WEBHOOK_ADDR_ENV, &addr)?;
+        Ok(Some(Self { url, secret, addr }))
+    }
+}
+
+fn required(name: &str) -> anyhow::Result<String> {
+    optional(name)?.ok_or_else(|| anyhow::anyhow!("you must define the {} env variable", name))
+}
+
+fn optional(name: &str) -> anyhow::Result<Option<String>> {
+    match env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(err) => Err(anyhow::anyhow!("invalid {} env variable: {}", name, err)),
+    }
+}
+
+fn parse_addr(name: &str, addr: &str) -> anyhow::Result<SocketAddr> {
+    addr.parse().map_err(|err| anyhow::anyhow!("invalid {} env variable: {}", name, err))
+}
+
+fn parse_operators(ids: &str) -> anyhow::Result<Vec<i64>> {
+    ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            id.parse().map_err(|_| anyhow::anyhow!("invalid user ID in {}: {}", OPERATORS_ENV, id))
+        })
+        .collect()
+}
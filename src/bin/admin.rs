@@ -0,0 +1,30 @@
+//! Operates on the database of the bot without Telegram, see
+//! `roff_bot::admin`. Reads `MONGO_OPTIONS`, `AUTOWARN_DATABASE` and
+//! `AUTOWARN_LEGACY_CHAT` like the bot:
+//!
+//! ```sh
+//! MONGO_OPTIONS=mongodb://localhost:27017 cargo run --bin admin -- warns -1001234567890 42
+//! ```
+
+use anyhow::Context;
+use mongodb::Client;
+use roff_bot::{admin, DatabaseConfig};
+use std::env;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    teloxide::enable_logging!();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args[0] == "help" || args[0] == "--help" {
+        println!("{}", admin::USAGE);
+        return Ok(());
+    }
+    let config = DatabaseConfig::from_env().context("invalid configuration")?;
+    let client =
+        Client::with_uri_str(&config.mongo_options).await.context("cannot reach MongoDB")?;
+    let db = client.database(&config.database);
+
+    print!("{}", admin::run(&db, config.legacy_chat, &args).await?);
+    Ok(())
+}
@@ -0,0 +1,37 @@
+//! Replays a recording made with `AUTOWARN_RECORD` and prints the Bot API
+//! calls which differ from the recorded ones:
+//!
+//! ```sh
+//! MONGO_OPTIONS=mongodb://localhost:27017 cargo run --bin replay -- recording.jsonl [chats.toml]
+//! ```
+//!
+//! Exits with 1 if the calls differ.
+
+use anyhow::Context;
+use roff_bot::replay::{self, ReplayOptions};
+use std::{env, path::PathBuf, process};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    teloxide::enable_logging!();
+
+    let mut args = env::args_os().skip(1);
+    let recording = match args.next() {
+        Some(path) => PathBuf::from(path),
+        None => anyhow::bail!("usage: replay <recording> [chats config]"),
+    };
+    let options = ReplayOptions {
+        recording,
+        mongo_options: env::var("MONGO_OPTIONS")
+            .context("you must define the MONGO_OPTIONS env variable")?,
+        chats_config: args.next().map(PathBuf::from),
+    };
+
+    let diff = replay::run(&options).await?;
+    if diff.is_empty() {
+        println!("The bot made the recorded calls");
+        return Ok(());
+    }
+    print!("{}", diff);
+    process::exit(1);
+}
@@ -0,0 +1,47 @@
+//! Delayed deletion of messages. Deletions are scheduled as jobs, so they are
+//! performed even if the bot was restarted in the meantime.
+
+use crate::{
+    jobs::{Job, Scheduler},
+    settings::ChatSettings,
+    utils, TBot,
+};
+use teloxide::{prelude2::*, types::ParseMode};
+
+#[derive(Debug, Clone)]
+pub struct CleanupQueue {
+    scheduler: Scheduler,
+}
+
+impl CleanupQueue {
+    pub fn new(scheduler: Scheduler) -> Self {
+        Self { scheduler }
+    }
+
+    pub async fn schedule(
+        &self,
+        chat_id: i64,
+        message_id: i32,
+        delay_secs: u64,
+    ) -> Result<(), mongodb::error::Error> {
+        self.scheduler.enqueue_in(Job::DeleteMessage { chat_id, message_id }, delay_secs).await
+    }
+
+    /// Sends an HTML announcement to the chat and schedules its deletion if the
+    /// chat wants so.
+    pub async fn announce(
+        &self,
+        bot: &TBot,
+        settings: &ChatSettings,
+        text: impl Into<String>,
+    ) -> anyhow::Result<Message> {
+        let mes = utils::send_with_retry(
+            bot.send_message(settings.chat_id, text).parse_mode(ParseMode::Html),
+        )
+        .await?;
+        if let Some(ttl) = settings.announcement_ttl {
+            self.schedule(mes.chat.id, mes.id, ttl).await?;
+        }
+        Ok(mes)
+    }
+}
@@ -0,0 +1,271 @@
+//! Declarative configuration of chats: their settings, warn groups, warn types
+//! and auto triggers. The configuration is read from a TOML file and synced
+//! into the database at startup. Chat owners can also export and import the
+//! configuration of their chat with `/exportconfig` and `/importconfig`.
+//!
+//! ```toml
+//! [[chats]]
+//! id = -1001234567890
+//! language = "ru"
+//!
+//! [[chats.groups]]
+//! name = "царизм"
+//! max_points = 100
+//! punishment = { kind = "mute", time = "forever" }
+//!
+//! [[chats.warn_types]]
+//! trigger = "макака"
+//! points = 30
+//! group = "царизм"
+//! on_warn = "delete_message"
+//!
+//! [[chats.auto_triggers]]
+//! pattern = "макака"
+//! trigger = "макака"
+//! ```
+
+pub(crate) mod commands;
+mod export;
+mod handlers;
+mod sync;
+
+use crate::{
+    i18n::Lang,
+    settings::PunishmentEndNotice,
+    templates::{is_valid_html, TemplateKey, Templates},
+    warnings::{OnWarnAction, Punishment, PunishmentKind, PunishmentTime},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, path::Path};
+use teloxide::types::ChatPermissions;
+
+pub use export::export_chat;
+pub use handlers::{setup_config_callback_queries_handler, setup_config_handler, PendingImports};
+pub use sync::{sync, SyncReport};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub chats: Vec<ChatConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChatConfig {
+    pub id: i64,
+    /// Settings which are not specified are left as they are.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<Lang>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete_commands: Option<bool>,
+    /// Seconds, `0` disables deletion of announcements.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub announcement_ttl: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub punishment_end_notice: Option<PunishmentEndNotice>,
+    // TOML cannot have values after tables, so tables and arrays of tables go
+    // last and empty arrays are left out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warn_types: Vec<WarnTypeConfig>,
+    /// Replaces all auto triggers of the chat if given. Otherwise they are left
+    /// as they are, e.g. the ones imported with `/importconfig`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_triggers: Option<Vec<AutoTriggerConfig>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub templates: Option<Templates>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupConfig {
+    pub name: String,
+    pub max_points: u64,
+    pub punishment: PunishmentConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub templates: Option<Templates>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PunishmentConfig {
+    pub kind: PunishmentKindConfig,
+    pub time: PunishmentTimeConfig,
+    /// What a restricted user can do. Required for the `restrict` kind only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<ChatPermissions>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PunishmentKindConfig {
+    Ban,
+    Mute,
+    Restrict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PunishmentTimeConfig {
+    /// Seconds.
+    Seconds(u64),
+    /// Only `"forever"` is allowed.
+    Forever(Forever),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Forever {
+    Forever,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WarnTypeConfig {
+    pub trigger: String,
+    pub points: u64,
+    pub group: String,
+    pub on_warn: OnWarnConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub templates: Option<Templates>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnWarnConfig {
+    DeleteMessage,
+    Nothing,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AutoTriggerConfig {
+    pub pattern: String,
+    pub trigger: String,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("cannot read {}: {}", path.display(), err))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let config: Self = toml::from_str(text)?;
+        let errors = config.validate();
+        if !errors.is_empty() {
+            anyhow::bail!("invalid configuration:\n{}", errors.join("\n"));
+        }
+        Ok(config)
+    }
+
+    /// Returns descriptions of all problems in the configuration.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        let mut chat_ids = HashSet::new();
+        for chat in &self.chats {
+            if !chat_ids.insert(chat.id) {
+                errors.push(format!("chat {} is defined twice", chat.id));
+            }
+            errors.extend(chat.validate().into_iter().map(|e| format!("chat {}: {}", chat.id, e)));
+        }
+        errors
+    }
+}
+
+impl ChatConfig {
+    /// Returns descriptions of all problems in the chat configuration.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        validate_templates(&self.templates, "chat", &mut errors);
+
+        let mut groups = HashSet::new();
+        for group in &self.groups {
+            if !groups.insert(group.name.as_str()) {
+                errors.push(format!("group `{}` is defined twice", group.name));
+            }
+            if group.max_points == 0 {
+                errors.push(format!("group `{}`: max_points must be positive", group.name));
+            }
+            let p = &group.punishment;
+            if p.kind == PunishmentKindConfig::Restrict && p.permissions.is_none() {
+                errors.push(format!("group `{}`: restrict requires permissions", group.name));
+            }
+            if p.kind != PunishmentKindConfig::Restrict && p.permissions.is_some() {
+                errors.push(format!("group `{}`: permissions are for restrict only", group.name));
+            }
+            validate_templates(&group.templates, &format!("group `{}`", group.name), &mut errors);
+        }
+
+        let mut triggers = HashSet::new();
+        for ty in &self.warn_types {
+            if !triggers.insert(ty.trigger.as_str()) {
+                errors.push(format!("warn type `{}` is defined twice", ty.trigger));
+            }
+            if ty.trigger.split_whitespace().count() != 1 {
+                errors.push(format!("warn type `{}`: trigger must be a single word", ty.trigger));
+            }
+            if ty.points == 0 {
+                errors.push(format!("warn type `{}`: points must be positive", ty.trigger));
+            }
+            if !groups.contains(ty.group.as_str()) {
+                errors.push(format!("warn type `{}`: unknown group `{}`", ty.trigger, ty.group));
+            }
+            validate_templates(&ty.templates, &format!("warn type `{}`", ty.trigger), &mut errors);
+        }
+
+        for rule in self.auto_triggers.iter().flatten() {
+            if rule.pattern.trim().is_empty() {
+                errors.push("auto trigger: pattern must not be empty".to_string());
+            }
+            if !triggers.contains(rule.trigger.as_str()) {
+                errors.push(format!(
+                    "auto trigger `{}`: unknown warn type `{}`",
+                    rule.pattern, rule.trigger
+                ));
+            }
+        }
+        errors
+    }
+}
+
+fn validate_templates(templates: &Option<Templates>, owner: &str, errors: &mut Vec<String>) {
+    for (key, template) in templates.iter().flatten() {
+        if TemplateKey::from_name(key).is_none() {
+            errors.push(format!("{}: unknown template `{}`", owner, key));
+        }
+        if !is_valid_html(template) {
+            errors.push(format!("{}: template `{}` is not valid HTML", owner, key));
+        }
+    }
+}
+
+impl From<&PunishmentConfig> for Punishment {
+    fn from(config: &PunishmentConfig) -> Self {
+        let kind = match config.kind {
+            PunishmentKindConfig::Ban => PunishmentKind::Ban,
+            PunishmentKindConfig::Mute => PunishmentKind::Mute,
+            PunishmentKindConfig::Restrict => {
+                PunishmentKind::Restrict(config.permissions.clone().unwrap_or_default())
+            }
+        };
+        let time = match config.time {
+            PunishmentTimeConfig::Seconds(secs) => PunishmentTime::Time(secs),
+            PunishmentTimeConfig::Forever(_) => PunishmentTime::Forever,
+        };
+        Punishment { time, kind }
+    }
+}
+
+impl From<OnWarnConfig> for OnWarnAction {
+    fn from(config: OnWarnConfig) -> Self {
+        match config {
+            OnWarnConfig::DeleteMessage => OnWarnAction::DeleteMessage,
+            OnWarnConfig::Nothing => OnWarnAction::Nothing,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+use teloxide::utils::command::BotCommand;
+
+#[derive(Debug, Clone, BotCommand)]
+#[command(rename = "lowercase")]
+pub enum ConfigCommand {
+    ExportConfig,
+    ImportConfig,
+}
@@ -0,0 +1,213 @@
+use crate::{
+    config::{
+        AutoTriggerConfig, ChatConfig, Forever, GroupConfig, OnWarnConfig, PunishmentConfig,
+        PunishmentKindConfig, PunishmentTimeConfig, WarnTypeConfig,
+    },
+    settings::{ChatSettings, SettingsRepository},
+    templates::Templates,
+    warnings::{
+        AutoTrigger, OnWarnAction, Punishment, PunishmentKind, PunishmentTime, WarningGroup,
+        WarningInfo, WarnsRepository,
+    },
+};
+
+/// Reads the current configuration of the chat from the database.
+pub async fn export_chat(
+    chat_id: i64,
+    repo: &WarnsRepository,
+    settings_repo: &SettingsRepository,
+) -> anyhow::Result<ChatConfig> {
+    let settings = settings_repo.get(chat_id).await?;
+    let groups = repo.get_warn_groups(chat_id).await?;
+    let warn_types = repo.get_warn_types(chat_id).await?;
+    let auto_triggers = repo.get_auto_triggers(chat_id).await?;
+    Ok(chat_config(settings, groups, warn_types, auto_triggers))
+}
+
+fn chat_config(
+    settings: ChatSettings,
+    groups: Vec<WarningGroup>,
+    warn_types: Vec<WarningInfo>,
+    auto_triggers: Vec<AutoTrigger>,
+) -> ChatConfig {
+    let chat_id = settings.chat_id;
+    let warn_types = warn_types
+        .into_iter()
+        .filter_map(|ty| {
+            let group = match groups.iter().find(|g| g.id == ty.group_id) {
+                Some(group) => group,
+                None => {
+                    log::warn!("Warn type `{}` in chat {} has no group", ty.trigger, chat_id);
+                    return None;
+                }
+            };
+            Some(WarnTypeConfig {
+                trigger: ty.trigger,
+                points: ty.points,
+                group: group.name.clone(),
+                on_warn: ty.on_warn.into(),
+                templates: non_empty(ty.templates),
+            })
+        })
+        .collect();
+    let groups = groups
+        .into_iter()
+        .map(|group| GroupConfig {
+            name: group.name,
+            max_points: group.max_points,
+            punishment: (&group.punishment).into(),
+            templates: non_empty(group.templates),
+        })
+        .collect();
+    let auto_triggers: Vec<_> = auto_triggers
+        .into_iter()
+        .map(|t| AutoTriggerConfig { pattern: t.pattern, trigger: t.trigger })
+        .collect();
+
+    ChatConfig {
+        id: chat_id,
+        language: settings.language,
+        delete_commands: Some(settings.delete_commands),
+        announcement_ttl: Some(settings.announcement_ttl.unwrap_or(0)),
+        punishment_end_notice: Some(settings.punishment_end_notice),
+        groups,
+        warn_types,
+        auto_triggers: Some(auto_triggers).filter(|t| !t.is_empty()),
+        templates: non_empty(settings.templates),
+    }
+}
+
+fn non_empty(templates: Templates) -> Option<Templates> {
+    Some(templates).filter(|t| !t.is_empty())
+}
+
+/// Describes what syncing `new` would change in the chat configured as `old`,
+/// one line per item: `+` for created items and `~` for changed ones.
+pub fn diff(old: &ChatConfig, new: &ChatConfig) -> Vec<String> {
+    let mut changes = vec![];
+    if changed(&new.language, &old.language) {
+        changes.push("~ language".to_string());
+    }
+    if changed(&new.delete_commands, &old.delete_commands) {
+        changes.push("~ delete_commands".to_string());
+    }
+    if changed(&new.announcement_ttl, &old.announcement_ttl) {
+        changes.push("~ announcement_ttl".to_string());
+    }
+    if changed(&new.punishment_end_notice, &old.punishment_end_notice) {
+        changes.push("~ punishment_end_notice".to_string());
+    }
+    if templates_changed(&new.templates, &old.templates) {
+        changes.push("~ templates".to_string());
+    }
+
+    for group in &new.groups {
+        match old.groups.iter().find(|g| g.name == group.name) {
+            None => changes.push(format!("+ groups.{}", group.name)),
+            Some(old) => {
+                if group.max_points != old.max_points
+                    || group.punishment != old.punishment
+                    || templates_changed(&group.templates, &old.templates)
+                {
+                    changes.push(format!("~ groups.{}", group.name));
+                }
+            }
+        }
+    }
+    for ty in &new.warn_types {
+        match old.warn_types.iter().find(|t| t.trigger == ty.trigger) {
+            None => changes.push(format!("+ warn_types.{}", ty.trigger)),
+            Some(old) => {
+                if ty.points != old.points
+                    || ty.group != old.group
+                    || ty.on_warn != old.on_warn
+                    || templates_changed(&ty.templates, &old.templates)
+                {
+                    changes.push(format!("~ warn_types.{}", ty.trigger));
+                }
+            }
+        }
+    }
+    let old_triggers = old.auto_triggers.clone().unwrap_or_default();
+    if matches!(&new.auto_triggers, Some(new) if *new != old_triggers) {
+        changes.push("~ auto_triggers".to_string());
+    }
+    changes
+}
+
+/// Settings which are not given are kept, so only given ones can change.
+fn changed<T: PartialEq>(new: &Option<T>, old: &Option<T>) -> bool {
+    matches!(new, Some(new) if Some(new) != old.as_ref())
+}
+
+/// Templates which are not given are kept, so only given ones can change.
+fn templates_changed(new: &Option<Templates>, old: &Option<Templates>) -> bool {
+    matches!(new, Some(new) if *new != old.clone().unwrap_or_default())
+}
+
+impl From<&Punishment> for PunishmentConfig {
+    fn from(punishment: &Punishment) -> Self {
+        let (kind, permissions) = match &punishment.kind {
+            PunishmentKind::Ban => (PunishmentKindConfig::Ban, None),
+            PunishmentKind::Mute => (PunishmentKindConfig::Mute, None),
+            PunishmentKind::Restrict(perms) => {
+                (PunishmentKindConfig::Restrict, Some(perms.clone()))
+            }
+        };
+        let time = match punishment.time {
+            PunishmentTime::Time(secs) => PunishmentTimeConfig::Seconds(secs),
+            PunishmentTime::Forever => PunishmentTimeConfig::Forever(Forever::Forever),
+        };
+        PunishmentConfig { kind, time, permissions }
+    }
+}
+
+impl From<OnWarnAction> for OnWarnConfig {
+    fn from(action: OnWarnAction) -> Self {
+        match action {
+            OnWarnAction::DeleteMessage => OnWarnConfig::DeleteMessage,
+            OnWarnAction::Nothing => OnWarnConfig::Nothing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config, i18n::Lang, migrations::SCHEMA_VERSION, settings::PunishmentEndNotice,
+    };
+    use mongodb::bson::oid::ObjectId;
+
+    fn settings() -> ChatSettings {
+        ChatSettings {
+            chat_id: -1001,
+            delete_commands: true,
+            announcement_ttl: None,
+            templates: [("warned".to_string(), "<b>{user}</b> warned".to_string())]
+                .into_iter()
+                .collect(),
+            language: Some(Lang::Ru),
+            punishment_end_notice: PunishmentEndNotice::Private,
+        }
+    }
+
+    #[test]
+    fn exports_chat_without_warn_types_and_auto_triggers() {
+        let group = WarningGroup {
+            id: ObjectId::new(),
+            chat_id: -1001,
+            name: "spam".to_string(),
+            max_points: 100,
+            punishment: Punishment { time: PunishmentTime::Forever, kind: PunishmentKind::Ban },
+            templates: Templates::new(),
+            schema_version: SCHEMA_VERSION,
+        };
+        let chat = chat_config(settings(), vec![group], vec![], vec![]);
+        assert_eq!(chat.auto_triggers, None);
+
+        let config = Config { chats: vec![chat] };
+        let document = toml::to_string_pretty(&config).unwrap();
+        assert_eq!(Config::parse(&document).unwrap(), config);
+    }
+}
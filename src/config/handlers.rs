@@ -0,0 +1,200 @@
+use crate::{
+    config::{self, commands::ConfigCommand, export, ChatConfig, Config},
+    i18n::{self, Text},
+    metrics,
+    settings::SettingsRepository,
+    templates::Placeholders,
+    utils,
+    warnings::WarnsRepository,
+    HandlerOut, TBot,
+};
+use std::{sync::Arc, time::Duration};
+use teloxide::{
+    net::Download,
+    prelude2::*,
+    types::{
+        InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, InputFile, ParseMode,
+    },
+};
+
+const IMPORT_PREFIX: &str = "import_config:";
+const APPLY: &str = "apply";
+const CANCEL: &str = "cancel";
+
+/// Bigger documents are surely not configurations.
+const MAX_DOCUMENT_SIZE: u32 = 64 * 1024;
+
+/// Imports the owner did not apply or cancel within this time are forgotten.
+const IMPORT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Imports that wait for the chat owner to confirm them. Keyed by the chat ID
+/// and the ID of the message with the diff.
+pub struct PendingImports(utils::Pending<(i64, i32), ChatConfig>);
+
+impl Default for PendingImports {
+    fn default() -> Self {
+        Self(utils::Pending::new(IMPORT_TTL))
+    }
+}
+
+pub fn setup_config_handler(bot_name: &str, pending: Arc<PendingImports>) -> crate::Handler {
+    utils::filter_chat_owner()
+        .add_command::<ConfigCommand>(bot_name.into())
+        .chain(dptree::filter_map(move || {
+            let pending = pending.clone();
+            async move { Some(pending) }
+        }))
+        .endpoint(handle_config_commands)
+}
+
+pub fn setup_config_callback_queries_handler(pending: Arc<PendingImports>) -> crate::Handler {
+    dptree::filter(|q: CallbackQuery| async move {
+        matches!(&q.data, Some(data) if data.starts_with(IMPORT_PREFIX))
+    })
+    .chain(dptree::filter_map(move || {
+        let pending = pending.clone();
+        async move { Some(pending) }
+    }))
+    .endpoint(confirm_import)
+}
+
+async fn handle_config_commands(
+    bot: TBot,
+    mes: Message,
+    cmd: ConfigCommand,
+    repo: WarnsRepository,
+    settings_repo: SettingsRepository,
+    pending: Arc<PendingImports>,
+) -> HandlerOut {
+    let _timer = metrics::handler_timer("config_commands");
+    let chat_id = mes.chat.id;
+    let lang = settings_repo.get(chat_id).await?.lang();
+    let send = |text: Text, placeholders: Placeholders| {
+        bot.send_message(chat_id, i18n::render(lang, text, &placeholders))
+            .parse_mode(ParseMode::Html)
+    };
+    let current = export::export_chat(chat_id, &repo, &settings_repo).await?;
+
+    match cmd {
+        ConfigCommand::ExportConfig => {
+            let document = toml::to_string_pretty(&Config { chats: vec![current] })?;
+            let file = InputFile::memory(format!("config_{}.toml", chat_id), document.into_bytes());
+            bot.send_document(chat_id, file).await?;
+        }
+        ConfigCommand::ImportConfig => {
+            let document = match mes.reply_to_message().and_then(|m| m.document()) {
+                Some(document) => document,
+                None => {
+                    send(Text::ImportReplyToDocument, Placeholders::new()).await?;
+                    return Ok(());
+                }
+            };
+            if document.file_size.unwrap_or(0) > MAX_DOCUMENT_SIZE {
+                send(Text::ImportTooLarge, Placeholders::new()).await?;
+                return Ok(());
+            }
+            let file = bot.get_file(&document.file_id).await?;
+            let mut content = vec![];
+            bot.download_file(&file.file_path, &mut content).await?;
+
+            let mut new = match parse_chat_config(&content) {
+                Ok(new) => new,
+                Err(err) => {
+                    let placeholders = Placeholders::new().text("errors", err);
+                    send(Text::ImportInvalid, placeholders).await?;
+                    return Ok(());
+                }
+            };
+            // The configuration may be exported from another chat.
+            new.id = chat_id;
+            let changes = export::diff(&current, &new);
+            if changes.is_empty() {
+                send(Text::ImportNoChanges, Placeholders::new()).await?;
+                return Ok(());
+            }
+
+            let button = |text: Text, action: &str| {
+                InlineKeyboardButton::new(
+                    i18n::text(lang, text),
+                    InlineKeyboardButtonKind::CallbackData(format!("{}{}", IMPORT_PREFIX, action)),
+                )
+            };
+            let kb = InlineKeyboardMarkup::new([[
+                button(Text::ApplyButton, APPLY),
+                button(Text::CancelButton, CANCEL),
+            ]]);
+            let placeholders = Placeholders::new().text("changes", changes.join("\n"));
+            let diff_mes = send(Text::ImportDiff, placeholders).reply_markup(kb).await?;
+            pending.0.insert((chat_id, diff_mes.id), new);
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_chat_config(content: &[u8]) -> anyhow::Result<ChatConfig> {
+    let text = std::str::from_utf8(content)?;
+    let mut config = Config::parse(text)?;
+    match config.chats.len() {
+        1 => Ok(config.chats.remove(0)),
+        n => anyhow::bail!("expected configuration of 1 chat, found {}", n),
+    }
+}
+
+async fn confirm_import(
+    bot: TBot,
+    q: CallbackQuery,
+    repo: WarnsRepository,
+    settings_repo: SettingsRepository,
+    pending: Arc<PendingImports>,
+) -> HandlerOut {
+    let mes = match &q.message {
+        Some(mes) => mes,
+        None => return Ok(()),
+    };
+    let action = q.data.as_deref().and_then(|data| data.strip_prefix(IMPORT_PREFIX));
+
+    let lang = settings_repo.get(mes.chat.id).await?.lang();
+    let member = bot.get_chat_member(mes.chat.id, q.from.id).await?;
+    if !member.kind.is_owner() {
+        bot.answer_callback_query(q.id)
+            .text(i18n::text(lang, Text::OnlyOwnerConfirms))
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+    bot.answer_callback_query(q.id).await?;
+
+    let new = pending.0.remove(&(mes.chat.id, mes.id));
+    let new = match new {
+        Some(new) => new,
+        None => {
+            let text = i18n::text(lang, Text::AlreadyHandled);
+            bot.edit_message_text(mes.chat.id, mes.id, text).await?;
+            return Ok(());
+        }
+    };
+    let text = match action {
+        Some(APPLY) => {
+            match config::sync(&Config { chats: vec![new] }, &repo, &settings_repo).await {
+                Ok(report) => {
+                    log::info!("Imported the configuration of chat {}: {}", mes.chat.id, report);
+                    Text::ImportApplied
+                }
+                // A warn type or a group was created concurrently with the import.
+                Err(err)
+                    if err
+                        .downcast_ref::<mongodb::error::Error>()
+                        .map_or(false, utils::is_duplicate_key) =>
+                {
+                    Text::ImportConflict
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        _ => Text::ImportCancelled,
+    };
+    bot.edit_message_text(mes.chat.id, mes.id, i18n::text(lang, text)).await?;
+
+    Ok(())
+}
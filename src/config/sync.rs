@@ -0,0 +1,151 @@
+use crate::{
+    config::{ChatConfig, Config},
+    migrations::SCHEMA_VERSION,
+    settings::SettingsRepository,
+    warnings::{AutoTrigger, Upserted, WarningGroup, WarningInfo, WarnsRepository},
+};
+use mongodb::bson::oid::ObjectId;
+use std::fmt;
+
+/// What syncing the configuration changed in the database.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub created: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+impl SyncReport {
+    fn add(&mut self, outcome: Upserted, item: String) {
+        match outcome {
+            Upserted::Created => self.created.push(item),
+            Upserted::Changed => self.changed.push(item),
+            Upserted::Unchanged => self.unchanged.push(item),
+        }
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} created, {} changed, {} unchanged",
+            self.created.len(),
+            self.changed.len(),
+            self.unchanged.len()
+        )?;
+        for item in &self.created {
+            write!(f, "\n  + {}", item)?;
+        }
+        for item in &self.changed {
+            write!(f, "\n  ~ {}", item)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the configuration to the database. Groups and warn types which are
+/// not in the configuration are left untouched, so the ones created with
+/// `/newwarn` survive restarts.
+pub async fn sync(
+    config: &Config,
+    repo: &WarnsRepository,
+    settings_repo: &SettingsRepository,
+) -> anyhow::Result<SyncReport> {
+    let mut report = SyncReport::default();
+    for chat in &config.chats {
+        sync_chat(chat, repo, settings_repo, &mut report).await?;
+    }
+    Ok(report)
+}
+
+async fn sync_chat(
+    chat: &ChatConfig,
+    repo: &WarnsRepository,
+    settings_repo: &SettingsRepository,
+    report: &mut SyncReport,
+) -> anyhow::Result<()> {
+    let chat_id = chat.id;
+    if let Some(lang) = chat.language {
+        settings_repo.set_language(chat_id, lang).await?;
+    }
+    if let Some(value) = chat.delete_commands {
+        settings_repo.set_delete_commands(chat_id, value).await?;
+    }
+    if let Some(secs) = chat.announcement_ttl {
+        settings_repo.set_announcement_ttl(chat_id, Some(secs).filter(|&s| s > 0)).await?;
+    }
+    if let Some(notice) = chat.punishment_end_notice {
+        settings_repo.set_punishment_end_notice(chat_id, notice).await?;
+    }
+    if let Some(templates) = &chat.templates {
+        settings_repo.set_templates(chat_id, templates).await?;
+    }
+
+    let mut groups = vec![];
+    for config in &chat.groups {
+        let old = repo.get_warn_group(chat_id, &config.name).await?;
+        let group = WarningGroup {
+            // Warn types and warns reference the group by ID, so it must not change.
+            id: old.as_ref().map(|g| g.id).unwrap_or_else(ObjectId::new),
+            chat_id,
+            name: config.name.clone(),
+            max_points: config.max_points,
+            punishment: (&config.punishment).into(),
+            // Templates set with `/settemplate` are kept unless the config overrides them.
+            templates: match &config.templates {
+                Some(templates) => templates.clone(),
+                None => old.map(|g| g.templates).unwrap_or_default(),
+            },
+            schema_version: SCHEMA_VERSION,
+        };
+        let outcome = repo.upsert_group(group.clone()).await?;
+        report.add(outcome, format!("chat {}: group `{}`", chat_id, group.name));
+        groups.push(group);
+    }
+
+    for config in &chat.warn_types {
+        let group = groups.iter().find(|g| g.name == config.group).ok_or_else(|| {
+            anyhow::anyhow!(
+                "chat {}: warn type `{}` refers to unknown group `{}`",
+                chat_id,
+                config.trigger,
+                config.group
+            )
+        })?;
+        let old = repo.find_warn_by_trigger(chat_id, &config.trigger).await?;
+        let ty = WarningInfo {
+            id: old.as_ref().map(|t| t.id).unwrap_or_else(ObjectId::new),
+            chat_id,
+            trigger: config.trigger.clone(),
+            points: config.points,
+            group_id: group.id,
+            on_warn: config.on_warn.into(),
+            templates: match &config.templates {
+                Some(templates) => templates.clone(),
+                None => old.map(|t| t.templates).unwrap_or_default(),
+            },
+            schema_version: SCHEMA_VERSION,
+        };
+        let outcome = repo.upsert_warn_type(ty).await?;
+        report.add(outcome, format!("chat {}: warn type `{}`", chat_id, config.trigger));
+    }
+
+    let triggers = match &chat.auto_triggers {
+        Some(triggers) => triggers,
+        None => return Ok(()),
+    };
+    let triggers = triggers
+        .iter()
+        .map(|t| AutoTrigger { chat_id, pattern: t.pattern.clone(), trigger: t.trigger.clone() })
+        .collect::<Vec<_>>();
+    let outcome = if repo.get_auto_triggers(chat_id).await? == triggers {
+        Upserted::Unchanged
+    } else {
+        repo.replace_auto_triggers(chat_id, triggers).await?;
+        Upserted::Changed
+    };
+    report.add(outcome, format!("chat {}: auto triggers", chat_id));
+
+    Ok(())
+}
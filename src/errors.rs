@@ -0,0 +1,227 @@
+//! Reporting of handler errors. Users who asked the bot to do something get a
+//! short explanation with an error ID, and operators of the bot get the details
+//! with the same ID in private messages.
+//!
+//! The chat to report to is attached to errors where handler trees are built,
+//! with [`message_errors`] and [`callback_errors`], so handlers just return
+//! their errors.
+
+use crate::{
+    i18n::{self, Lang, Text},
+    metrics,
+    settings::SettingsRepository,
+    templates::Placeholders,
+    Handler, TBot,
+};
+use dptree::di::{DependencyMap, DependencySupplier};
+use mongodb::bson::oid::ObjectId;
+use std::{fmt, future::Future, ops::ControlFlow, pin::Pin, sync::Arc};
+use teloxide::{
+    error_handlers::ErrorHandler, prelude2::*, types::ParseMode, ApiError, RequestError,
+};
+
+/// Telegram does not accept longer messages.
+const MAX_MESSAGE_LEN: usize = 4096;
+
+/// Where the update that caused an error came from, so the error can be
+/// reported to the chat.
+#[derive(Debug, Clone, Copy)]
+pub struct InChat {
+    pub chat_id: i64,
+    /// The message to reply to with the report.
+    pub message_id: Option<i32>,
+    /// Whether only operators are told about the error, e.g. because nobody in
+    /// the chat asked the bot to do anything.
+    pub quiet: bool,
+}
+
+impl InChat {
+    pub fn message(mes: &Message) -> Self {
+        Self { chat_id: mes.chat.id, message_id: Some(mes.id), quiet: false }
+    }
+
+    /// Errors of buttons under inline messages are reported to the user who
+    /// pressed the button.
+    pub fn callback(q: &CallbackQuery) -> Self {
+        match &q.message {
+            Some(mes) => Self::message(mes),
+            None => Self { chat_id: q.from.id, message_id: None, quiet: false },
+        }
+    }
+
+    pub fn quiet(self) -> Self {
+        Self { quiet: true, ..self }
+    }
+}
+
+impl fmt::Display for InChat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message_id {
+            Some(id) => write!(f, "in chat {}, message {}", self.chat_id, id),
+            None => write!(f, "in chat {}", self.chat_id),
+        }
+    }
+}
+
+/// A mistake of the user, e.g. a malformed command argument. It is explained
+/// to the user with the text and not reported to operators.
+#[derive(Debug, Clone, Copy)]
+pub struct UserInputError(pub Text);
+
+impl fmt::Display for UserInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid user input: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UserInputError {}
+
+/// Attaches the chat of the message being handled to errors of the handlers
+/// chained after it.
+pub fn message_errors() -> Handler {
+    attach_chat(|deps| InChat::message(&DependencySupplier::<Message>::get(deps)))
+}
+
+/// Like [`message_errors`], but only operators are told about the errors.
+pub fn quiet_message_errors() -> Handler {
+    attach_chat(|deps| InChat::message(&DependencySupplier::<Message>::get(deps)).quiet())
+}
+
+/// Attaches the chat of the callback query being handled to errors of the
+/// handlers chained after it.
+pub fn callback_errors() -> Handler {
+    attach_chat(|deps| InChat::callback(&DependencySupplier::<CallbackQuery>::get(deps)))
+}
+
+/// Errors which already have a chat attached closer to their handler keep it.
+fn attach_chat(chat: fn(&DependencyMap) -> InChat) -> Handler {
+    dptree::from_fn(move |deps: DependencyMap, cont| async move {
+        let chat = chat(&deps);
+        match cont(deps).await {
+            ControlFlow::Break(Err(err)) if err.downcast_ref::<InChat>().is_none() => {
+                ControlFlow::Break(Err(err.context(chat)))
+            }
+            flow => flow,
+        }
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ErrorClass {
+    UserInput(Text),
+    Storage,
+    /// The bot lacks admin rights in the chat.
+    TelegramRights,
+    Telegram,
+    Other,
+}
+
+fn classify(error: &anyhow::Error) -> ErrorClass {
+    for cause in error.chain() {
+        if let Some(UserInputError(text)) = cause.downcast_ref::<UserInputError>() {
+            return ErrorClass::UserInput(*text);
+        }
+        if cause.is::<mongodb::error::Error>() {
+            return ErrorClass::Storage;
+        }
+        if let Some(err) = cause.downcast_ref::<RequestError>() {
+            return match err {
+                RequestError::ApiError { kind, .. } if lacks_rights(kind) => {
+                    ErrorClass::TelegramRights
+                }
+                _ => ErrorClass::Telegram,
+            };
+        }
+    }
+    ErrorClass::Other
+}
+
+fn lacks_rights(kind: &ApiError) -> bool {
+    match kind {
+        ApiError::NotEnoughRightsToRestrict
+        | ApiError::NotEnoughRightsToPinMessage
+        | ApiError::MessageCantBeDeleted => true,
+        ApiError::Unknown(description) => {
+            description.contains("not enough rights") || description.contains("CHAT_ADMIN_REQUIRED")
+        }
+        _ => false,
+    }
+}
+
+/// Logs errors of handlers and reports them to the chat and operators.
+pub struct ErrorReporter {
+    bot: TBot,
+    settings_repo: SettingsRepository,
+    /// IDs of users who get details of errors.
+    operators: Vec<i64>,
+}
+
+impl ErrorReporter {
+    pub fn new(bot: TBot, settings_repo: SettingsRepository, operators: Vec<i64>) -> Self {
+        Self { bot, settings_repo, operators }
+    }
+
+    async fn report(&self, error: anyhow::Error) {
+        let class = classify(&error);
+        let chat = error.downcast_ref::<InChat>().copied();
+        if let ErrorClass::UserInput(text) = class {
+            if let Some(chat) = chat {
+                self.reply(chat, text, &Placeholders::new()).await;
+            }
+            return;
+        }
+
+        let id = ObjectId::new().to_hex();
+        if matches!(class, ErrorClass::Telegram | ErrorClass::TelegramRights) {
+            metrics::telegram_error("handler");
+        }
+        log::error!("Error {} ({:?}): {:?}", id, class, error);
+
+        if let Some(chat) = chat.filter(|chat| !chat.quiet) {
+            let text = match class {
+                ErrorClass::Storage => Text::ErrorStorage,
+                ErrorClass::TelegramRights => Text::ErrorRights,
+                _ => Text::ErrorInternal,
+            };
+            self.reply(chat, text, &Placeholders::new().text("id", &id)).await;
+        }
+
+        let details = format!("Error {} ({:?}):\n{:?}", id, class, error);
+        let details: String = details.chars().take(MAX_MESSAGE_LEN).collect();
+        for &operator in &self.operators {
+            if let Err(err) = self.bot.send_message(operator, details.clone()).await {
+                log::warn!("Failed to report error {} to operator {}: {}", id, operator, err);
+                metrics::telegram_error("error_report");
+            }
+        }
+    }
+
+    async fn reply(&self, chat: InChat, text: Text, placeholders: &Placeholders) {
+        // The storage may be the reason of the error.
+        let lang = match self.settings_repo.get(chat.chat_id).await {
+            Ok(settings) => settings.lang(),
+            Err(_) => Lang::default(),
+        };
+        let mut request = self
+            .bot
+            .send_message(chat.chat_id, i18n::render(lang, text, placeholders))
+            .parse_mode(ParseMode::Html);
+        if let Some(id) = chat.message_id {
+            // The message may be gone, e.g. a command deleted with `delete_commands`.
+            request = request.reply_to_message_id(id).allow_sending_without_reply(true);
+        }
+        if let Err(err) = request.await {
+            log::warn!("Failed to report an error {}: {}", chat, err);
+            metrics::telegram_error("error_report");
+        }
+    }
+}
+
+impl ErrorHandler<anyhow::Error> for ErrorReporter {
+    fn handle_error(
+        self: Arc<Self>,
+        error: anyhow::Error,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        Box::pin(async move { self.report(error).await })
+    }
+}
@@ -0,0 +1,254 @@
+//! Localization of the bot texts. Every language has its own catalog of texts,
+//! default templates and command descriptions.
+
+mod en;
+mod ru;
+
+use crate::{
+    templates::{self, Placeholders, TemplateKey},
+    TBot,
+};
+use serde::{Deserialize, Serialize};
+use teloxide::{prelude2::*, types::User};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    En,
+    Ru,
+}
+
+impl Default for Lang {
+    fn default() -> Self {
+        Self::En
+    }
+}
+
+impl Lang {
+    pub const ALL: &'static [Lang] = &[Self::En, Self::Ru];
+
+    /// IETF language tag of the language.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Ru => "ru",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        // Telegram may send tags like `ru-RU`.
+        let code = code.split('-').next().unwrap_or(code);
+        Self::ALL.iter().copied().find(|lang| lang.code() == code)
+    }
+
+    /// Returns `chat_lang` if the chat has chosen a language, or the language of
+    /// the user otherwise.
+    pub fn resolve(chat_lang: Option<Lang>, user: Option<&User>) -> Self {
+        chat_lang
+            .or_else(|| user.and_then(|u| u.language_code.as_deref()).and_then(Self::from_code))
+            .unwrap_or_default()
+    }
+}
+
+/// Texts of the bot that cannot be overridden by templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Text {
+    /// Placeholders: `{id}`.
+    YourId,
+    /// Placeholders: `{id}`.
+    ChatId,
+    /// Placeholders: `{commands}`.
+    Help,
+
+    ReplyToWarn,
+    NoSuchWarnType,
+    /// Placeholders: `{rights}`.
+    MissingRights,
+    RightBanUsers,
+    RightDeleteMessages,
+    /// Placeholders: `{error}`.
+    DeleteFailed,
+    ProtectedSelf,
+    ProtectedOwner,
+    ProtectedAdmin,
+    ProtectedAnonymousAdmin,
+    ProtectedImmune,
+    /// The reason of warns given by auto triggers.
+    AutoWarnReason,
+    PunishmentBanned,
+    PunishmentMuted,
+    PunishmentRestricted,
+    UntilForever,
+    /// Placeholders: `{time}`.
+    UntilTime,
+
+    ReplyToImmune,
+    ReplyToUnimmune,
+    /// Placeholders: `{user}`.
+    Immune,
+    /// Placeholders: `{user}`.
+    Unimmune,
+    /// Placeholders: `{user}`.
+    NotImmune,
+    NoActivePunishments,
+    /// Placeholders: `{punishments}`.
+    ActivePunishments,
+    /// Placeholders: `{user}`, `{punishment}`, `{group}`, `{until}`, `{left}`.
+    ActivePunishment,
+    /// Placeholders: `{n}`.
+    Days,
+    /// Placeholders: `{n}`.
+    Hours,
+    /// Placeholders: `{n}`.
+    Minutes,
+
+    AnonymousConfirmation,
+    ConfirmButton,
+    OnlyOwnerConfirms,
+    /// Placeholders: `{user}`.
+    ConfirmedBy,
+    AlreadyHandled,
+
+    DeleteMessageButton,
+    DoNothingButton,
+    Selected,
+
+    PointsButton,
+    GroupButton,
+    OnWarnButton,
+    DeleteButton,
+    /// Placeholders: `{trigger}`, `{points}`, `{group}`, `{on_warn}`.
+    EditWarnChoose,
+    EditAskPoints,
+    EditInvalidPoints,
+    EditAskGroup,
+    EditNoSuchGroup,
+    EditAskOnWarn,
+    /// Placeholders: `{trigger}`.
+    WarnTypeUpdated,
+    EditCancelled,
+    /// Placeholders: `{trigger}`, `{warns}`.
+    DeleteWarnConfirm,
+    /// Placeholders: `{trigger}`.
+    WarnTypeDeleted,
+
+    On,
+    Off,
+    /// Placeholders: `{secs}`.
+    Seconds,
+    /// Placeholders: `{delete_commands}`, `{announcement_ttl}`, `{language}`,
+    /// `{punishment_end}`.
+    Settings,
+    DeleteCommandsUsage,
+    AnnouncementTtlUsage,
+    LanguageUsage,
+    PunishmentEndUsage,
+    /// Placeholders: `{keys}`.
+    TemplatesList,
+    TemplateEmpty,
+    TemplateInvalidHtml,
+    /// Placeholders: `{preview}`.
+    TemplateSet,
+    TemplateReset,
+    NoSuchTemplate,
+    /// Placeholders: `{scope}`.
+    NoSuchScope,
+
+    ImportReplyToDocument,
+    ImportTooLarge,
+    /// Placeholders: `{errors}`.
+    ImportInvalid,
+    ImportNoChanges,
+    /// Placeholders: `{changes}`.
+    ImportDiff,
+    ApplyButton,
+    CancelButton,
+    ImportApplied,
+    ImportCancelled,
+    ImportConflict,
+    /// Placeholders: `{id}`.
+    ErrorStorage,
+    /// Placeholders: `{id}`.
+    ErrorRights,
+    /// Placeholders: `{id}`.
+    ErrorInternal,
+}
+
+pub fn text(lang: Lang, text: Text) -> &'static str {
+    match lang {
+        Lang::En => en::text(text),
+        Lang::Ru => ru::text(text),
+    }
+}
+
+/// Renders the text as HTML.
+pub fn render(lang: Lang, text: Text, placeholders: &Placeholders) -> String {
+    templates::substitute(self::text(lang, text), placeholders)
+}
+
+pub fn default_template(lang: Lang, key: TemplateKey) -> &'static str {
+    match lang {
+        Lang::En => en::template(key),
+        Lang::Ru => ru::template(key),
+    }
+}
+
+/// Descriptions of the bot commands, in the form of `(command, description)`.
+/// The command enums have no descriptions of their own.
+pub fn commands(lang: Lang) -> &'static [(&'static str, &'static str)] {
+    match lang {
+        Lang::En => en::COMMANDS,
+        Lang::Ru => ru::COMMANDS,
+    }
+}
+
+/// Registers localized command descriptions shown by Telegram clients. The
+/// English ones are used for users whose language is not supported.
+pub async fn register_commands(bot: &TBot) {
+    for &lang in Lang::ALL {
+        let commands = commands(lang)
+            .iter()
+            .map(|(command, description)| teloxide::types::BotCommand::new(*command, *description));
+        let mut request = bot.set_my_commands(commands);
+        if lang != Lang::default() {
+            request = request.language_code(lang.code());
+        }
+        if let Err(err) = request.await {
+            log::error!("Failed to register commands for {:?}: {}", lang, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::commands::ConfigCommand,
+        settings::commands::SettingsCommand,
+        simple_commands::SimpleCommands,
+        warnings::commands::{EditWarnsCommand, SetupWarnsCommands, WarnsCommand},
+    };
+    use teloxide::utils::command::BotCommand;
+
+    #[test]
+    fn every_command_is_described_in_every_language() {
+        let descriptions = [
+            SimpleCommands::descriptions(),
+            WarnsCommand::descriptions(),
+            SetupWarnsCommands::descriptions(),
+            EditWarnsCommand::descriptions(),
+            SettingsCommand::descriptions(),
+            ConfigCommand::descriptions(),
+        ];
+        let mut names: Vec<&str> = descriptions
+            .iter()
+            .flat_map(|d| d.split_whitespace().filter_map(|word| word.strip_prefix('/')))
+            .collect();
+        names.sort_unstable();
+        for &lang in Lang::ALL {
+            let mut described: Vec<&str> = commands(lang).iter().map(|(name, _)| *name).collect();
+            described.sort_unstable();
+            assert_eq!(described, names, "{:?}", lang);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{i18n::Text, templates::TemplateKey};
+
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("help", "shows this message."),
+    ("chatid", "shows this chat ID."),
+    ("myid", "shows your ID."),
+    ("warn", "warn a user in `/warn <trigger> [reason]` format."),
+    ("immune", "make the user you reply to immune to warns in this chat."),
+    ("unimmune", "remove the user you reply to from the immune list."),
+    ("punished", "list active punishments in the chat."),
+    ("newwarn", "create new warn type for the chat with specified id."),
+    ("cancel", "cancel creation of the warn."),
+    ("editwarn", "change points, group or on-warn action of a warn type."),
+    ("deletewarn", "delete a warn type."),
+    ("settings", "show settings of the chat."),
+    ("deletecommands", "delete `/warn` commands after handling: on or off."),
+    ("announcementttl", "delete bot announcements after the given seconds, or off."),
+    ("language", "set language of the bot in the chat: en or ru."),
+    ("punishmentend", "announce the end of punishments: chat, private or off."),
+    ("templates", "list keys of the templates."),
+    (
+        "settemplate",
+        "override a template in `/settemplate <key> [trigger=<t>|group=<g>] <text>` format.",
+    ),
+    (
+        "resettemplate",
+        "restore a default template in `/resettemplate <key> [trigger=<t>|group=<g>]` format.",
+    ),
+    ("exportconfig", "send the configuration of the chat as a TOML document."),
+    ("importconfig", "import the configuration from the TOML document you reply to."),
+];
+
+pub fn text(text: Text) -> &'static str {
+    match text {
+        Text::YourId => "Your ID: <code>{id}</code>",
+        Text::ChatId => "Chat ID: <code>{id}</code>",
+        Text::Help => "These commands are supported:\n{commands}",
+
+        Text::ReplyToWarn => "Reply to a user message to warn.",
+        Text::NoSuchWarnType => "There are no such warning type.",
+        Text::MissingRights => {
+            "I cannot warn users here. Give me the following admin rights: {rights}."
+        }
+        Text::RightBanUsers => "ban users",
+        Text::RightDeleteMessages => "delete messages",
+        Text::DeleteFailed => "I failed to delete the message: {error}.",
+        Text::ProtectedSelf => "I will not warn myself.",
+        Text::ProtectedOwner => "The chat owner cannot be warned.",
+        Text::ProtectedAdmin => "Chat administrators cannot be warned.",
+        Text::ProtectedAnonymousAdmin => "Anonymous administrators cannot be warned.",
+        Text::ProtectedImmune => "This user is immune to warns in this chat.",
+        Text::AutoWarnReason => "Automatic warn.",
+        Text::PunishmentBanned => "banned",
+        Text::PunishmentMuted => "muted",
+        Text::PunishmentRestricted => "restricted",
+        Text::UntilForever => "forever",
+        Text::UntilTime => "until {time}",
+
+        Text::ReplyToImmune => "Reply to a user message to make them immune.",
+        Text::ReplyToUnimmune => "Reply to a user message to remove them from the immune list.",
+        Text::Immune => "{user} is now immune to warns in this chat.",
+        Text::Unimmune => "{user} is no longer immune to warns.",
+        Text::NotImmune => "{user} is not in the immune list.",
+        Text::NoActivePunishments => "Nobody is punished now.",
+        Text::ActivePunishments => "Active punishments:\n{punishments}",
+        Text::ActivePunishment => "{user}: {punishment} {until} ({left} left), group {group}",
+        Text::Days => "{n}d",
+        Text::Hours => "{n}h",
+        Text::Minutes => "{n}m",
+
+        Text::AnonymousConfirmation => {
+            "I cannot check who sent this command anonymously. The chat owner must confirm it."
+        }
+        Text::ConfirmButton => "Confirm",
+        Text::OnlyOwnerConfirms => "Only the chat owner can confirm this command.",
+        Text::ConfirmedBy => "Confirmed by {user}.",
+        Text::AlreadyHandled => "This command is already handled.",
+
+        Text::DeleteMessageButton => "Delete message",
+        Text::DoNothingButton => "Do nothing",
+        Text::Selected => "Selected.",
+
+        Text::PointsButton => "Points",
+        Text::GroupButton => "Group",
+        Text::OnWarnButton => "On warn",
+        Text::DeleteButton => "Delete",
+        Text::EditWarnChoose => {
+            "Warn type <code>{trigger}</code> gives {points} points in group {group}. On warn: \
+             {on_warn}.\nWhat do you want to change?"
+        }
+        Text::EditAskPoints => "Send me the new amount of points.",
+        Text::EditInvalidPoints => "Send me a positive number of points.",
+        Text::EditAskGroup => "Send me the name of the new warn group.",
+        Text::EditNoSuchGroup => {
+            "There is no such warn group. Send me the name of an existing one."
+        }
+        Text::EditAskOnWarn => "Do you want to delete the message you reply to when warning?",
+        Text::WarnTypeUpdated => {
+            "Warn type <code>{trigger}</code> is updated. Warns given before are not changed."
+        }
+        Text::EditCancelled => "Cancelled.",
+        Text::DeleteWarnConfirm => {
+            "Delete warn type <code>{trigger}</code>? Its {warns} active warns will be moved to \
+             the history and stop counting, and auto triggers giving it will be removed."
+        }
+        Text::WarnTypeDeleted => "Warn type <code>{trigger}</code> is deleted.",
+
+        Text::On => "on",
+        Text::Off => "off",
+        Text::Seconds => "{secs} seconds",
+        Text::Settings => {
+            "Delete /warn commands: {delete_commands}.\nDelete announcements after: \
+             {announcement_ttl}.\nLanguage: {language}.\nAnnounce the end of punishments: \
+             {punishment_end}."
+        }
+        Text::DeleteCommandsUsage => "Use <code>/deletecommands on|off</code> format.",
+        Text::AnnouncementTtlUsage => {
+            "Use <code>/announcementttl &lt;seconds|off&gt;</code> format."
+        }
+        Text::LanguageUsage => "Use <code>/language en|ru</code> format.",
+        Text::PunishmentEndUsage => "Use <code>/punishmentend chat|private|off</code> format.",
+        Text::TemplatesList => {
+            "Templates can be overridden for the chat, a warn group or a warn type. Available \
+             keys:\n{keys}"
+        }
+        Text::TemplateEmpty => "The template text is empty.",
+        Text::TemplateInvalidHtml => {
+            "The template is not valid HTML. Close every tag and write &lt; and &amp; as \
+             <code>&amp;lt;</code> and <code>&amp;amp;</code>."
+        }
+        Text::TemplateSet => "Template is set. Preview:\n{preview}",
+        Text::TemplateReset => "The default template is restored.",
+        Text::NoSuchTemplate => "There is no such template. See /templates.",
+        Text::NoSuchScope => "There is no such warn type or group: {scope}.",
+
+        Text::ImportReplyToDocument => "Reply to a configuration document with /importconfig.",
+        Text::ImportTooLarge => "The document is too large to be a configuration.",
+        Text::ImportInvalid => "The configuration is invalid:\n<pre>{errors}</pre>",
+        Text::ImportNoChanges => "The configuration matches the current one, nothing to import.",
+        Text::ImportDiff => {
+            "The import will make these changes:\n<pre>{changes}</pre>\nApply them?"
+        }
+        Text::ApplyButton => "Apply",
+        Text::CancelButton => "Cancel",
+        Text::ImportApplied => "The configuration is imported.",
+        Text::ImportCancelled => "The import is cancelled.",
+        Text::ImportConflict => {
+            "A warn type or a group was created while importing. Check the configuration and \
+             import it again."
+        }
+        Text::ErrorStorage => {
+            "The database is not available now, try again later. Error ID: <code>{id}</code>."
+        }
+        Text::ErrorRights => "The bot lacks admin rights to do this. Error ID: <code>{id}</code>.",
+        Text::ErrorInternal => "Something went wrong. Error ID: <code>{id}</code>.",
+    }
+}
+
+pub fn template(key: TemplateKey) -> &'static str {
+    match key {
+        TemplateKey::Warned => "{user} has been warned! {points}/{max} points.\n{reason}",
+        TemplateKey::Punished => "{user} has been {punishment} {until}!\n{reason}",
+        TemplateKey::PunishFailed => {
+            "{user} has reached {points}/{max} points, but I failed to punish them: {error}. The \
+             warns are kept."
+        }
+        TemplateKey::PunishmentEnded => "{user} is no longer {punishment}.",
+        TemplateKey::SetupAlreadyStarted => "You already setup new warn type.",
+        TemplateKey::SetupAskGroup => {
+            "Good. Send me the name of the warn group the warn must relate to."
+        }
+        TemplateKey::SetupCancelled => "Cancelled.",
+        TemplateKey::SetupNoSuchGroup => {
+            "There are no such warn group. Send me the name of the warn group the warn must relate \
+             to."
+        }
+        TemplateKey::SetupAskPoints => {
+            "Good. Now send me amount of the points the user will receive by this warn."
+        }
+        TemplateKey::SetupInvalidPoints => {
+            "Send me the maximum points the user receive before they gets punished."
+        }
+        TemplateKey::SetupAskTrigger => {
+            "Good. Now send me a text trigger for the warn. It then will be used by <code>/warn \
+             &lt;trigger&gt;</code> format."
+        }
+        TemplateKey::SetupInvalidTrigger => "The trigger must be a single word.",
+        TemplateKey::SetupTriggerExists => "Warn with such trigger already exists.",
+        TemplateKey::SetupAskOnWarn => {
+            "Good. Do you want to delete the message you reply to when warning?"
+        }
+        TemplateKey::SetupUseButtons => "Please, use one of the buttons above.",
+        TemplateKey::SetupDone => {
+            "You have added new warn type. To use it use <code>/warn {trigger}</code> command."
+        }
+    }
+}
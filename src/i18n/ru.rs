@@ -0,0 +1,211 @@
+use crate::{i18n::Text, templates::TemplateKey};
+
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("help", "показать это сообщение."),
+    ("chatid", "показать ID этого чата."),
+    ("myid", "показать ваш ID."),
+    ("warn", "выдать предупреждение в формате `/warn <триггер> [причина]`."),
+    ("immune", "защитить пользователя, на сообщение которого вы отвечаете, от предупреждений."),
+    ("unimmune", "убрать пользователя, на сообщение которого вы отвечаете, из списка защищённых."),
+    ("punished", "показать действующие наказания в чате."),
+    ("newwarn", "создать новый тип предупреждения для чата с указанным ID."),
+    ("cancel", "отменить создание предупреждения."),
+    ("editwarn", "изменить баллы, группу или действие типа предупреждения."),
+    ("deletewarn", "удалить тип предупреждения."),
+    ("settings", "показать настройки чата."),
+    ("deletecommands", "удалять команды `/warn` после обработки: on или off."),
+    ("announcementttl", "удалять объявления бота через указанное число секунд, или off."),
+    ("language", "выбрать язык бота в чате: en или ru."),
+    ("punishmentend", "сообщать об окончании наказаний: chat, private или off."),
+    ("templates", "показать ключи шаблонов."),
+    (
+        "settemplate",
+        "переопределить шаблон в формате `/settemplate <ключ> [trigger=<t>|group=<g>] <текст>`.",
+    ),
+    (
+        "resettemplate",
+        "вернуть шаблон по умолчанию в формате `/resettemplate <ключ> [trigger=<t>|group=<g>]`.",
+    ),
+    ("exportconfig", "отправить настройки чата документом TOML."),
+    ("importconfig", "импортировать настройки из документа TOML, на который вы отвечаете."),
+];
+
+pub fn text(text: Text) -> &'static str {
+    match text {
+        Text::YourId => "Ваш ID: <code>{id}</code>",
+        Text::ChatId => "ID чата: <code>{id}</code>",
+        Text::Help => "Поддерживаются следующие команды:\n{commands}",
+
+        Text::ReplyToWarn => "Ответьте на сообщение пользователя, чтобы выдать предупреждение.",
+        Text::NoSuchWarnType => "Такого типа предупреждений нет.",
+        Text::MissingRights => {
+            "Я не могу выдавать предупреждения в этом чате. Дайте мне следующие права \
+             администратора: {rights}."
+        }
+        Text::RightBanUsers => "блокировка пользователей",
+        Text::RightDeleteMessages => "удаление сообщений",
+        Text::DeleteFailed => "Мне не удалось удалить сообщение: {error}.",
+        Text::ProtectedSelf => "Я не буду выдавать предупреждение самому себе.",
+        Text::ProtectedOwner => "Владельцу чата нельзя выдать предупреждение.",
+        Text::ProtectedAdmin => "Администраторам чата нельзя выдать предупреждение.",
+        Text::ProtectedAnonymousAdmin => "Анонимным администраторам нельзя выдать предупреждение.",
+        Text::ProtectedImmune => "Этот пользователь защищён от предупреждений в этом чате.",
+        Text::AutoWarnReason => "Автоматическое предупреждение.",
+        Text::PunishmentBanned => "заблокирован(а)",
+        Text::PunishmentMuted => "лишён(а) права голоса",
+        Text::PunishmentRestricted => "ограничен(а)",
+        Text::UntilForever => "навсегда",
+        Text::UntilTime => "до {time}",
+
+        Text::ReplyToImmune => {
+            "Ответьте на сообщение пользователя, чтобы защитить его от предупреждений."
+        }
+        Text::ReplyToUnimmune => {
+            "Ответьте на сообщение пользователя, чтобы убрать его из списка защищённых."
+        }
+        Text::Immune => "{user} теперь защищён(а) от предупреждений в этом чате.",
+        Text::Unimmune => "{user} больше не защищён(а) от предупреждений.",
+        Text::NotImmune => "{user} нет в списке защищённых.",
+        Text::NoActivePunishments => "Сейчас никто не наказан.",
+        Text::ActivePunishments => "Действующие наказания:\n{punishments}",
+        Text::ActivePunishment => "{user}: {punishment} {until} (осталось {left}), группа {group}",
+        Text::Days => "{n} д",
+        Text::Hours => "{n} ч",
+        Text::Minutes => "{n} мин",
+
+        Text::AnonymousConfirmation => {
+            "Я не могу проверить, кто анонимно отправил эту команду. Владелец чата должен её \
+             подтвердить."
+        }
+        Text::ConfirmButton => "Подтвердить",
+        Text::OnlyOwnerConfirms => "Только владелец чата может подтвердить эту команду.",
+        Text::ConfirmedBy => "Подтверждено: {user}.",
+        Text::AlreadyHandled => "Эта команда уже обработана.",
+
+        Text::DeleteMessageButton => "Удалить сообщение",
+        Text::DoNothingButton => "Ничего не делать",
+        Text::Selected => "Выбрано.",
+
+        Text::PointsButton => "Баллы",
+        Text::GroupButton => "Группа",
+        Text::OnWarnButton => "Действие",
+        Text::DeleteButton => "Удалить",
+        Text::EditWarnChoose => {
+            "Тип предупреждения <code>{trigger}</code> даёт {points} баллов в группе {group}. При \
+             предупреждении: {on_warn}.\nЧто вы хотите изменить?"
+        }
+        Text::EditAskPoints => "Отправьте новое число баллов.",
+        Text::EditInvalidPoints => "Отправьте положительное число баллов.",
+        Text::EditAskGroup => "Отправьте название новой группы предупреждений.",
+        Text::EditNoSuchGroup => {
+            "Такой группы предупреждений нет. Отправьте название существующей группы."
+        }
+        Text::EditAskOnWarn => "Удалять сообщение, на которое отвечают при выдаче предупреждения?",
+        Text::WarnTypeUpdated => {
+            "Тип предупреждения <code>{trigger}</code> изменён. Выданные ранее предупреждения не \
+             меняются."
+        }
+        Text::EditCancelled => "Отменено.",
+        Text::DeleteWarnConfirm => {
+            "Удалить тип предупреждения <code>{trigger}</code>? Его действующие предупреждения \
+             ({warns}) будут перенесены в историю и перестанут учитываться, а автотриггеры, \
+             которые его выдают, будут удалены."
+        }
+        Text::WarnTypeDeleted => "Тип предупреждения <code>{trigger}</code> удалён.",
+
+        Text::On => "вкл",
+        Text::Off => "выкл",
+        Text::Seconds => "{secs} сек.",
+        Text::Settings => {
+            "Удалять команды /warn: {delete_commands}.\nУдалять объявления через: \
+             {announcement_ttl}.\nЯзык: {language}.\nСообщать об окончании наказаний: \
+             {punishment_end}."
+        }
+        Text::DeleteCommandsUsage => "Используйте формат <code>/deletecommands on|off</code>.",
+        Text::AnnouncementTtlUsage => {
+            "Используйте формат <code>/announcementttl &lt;секунды|off&gt;</code>."
+        }
+        Text::LanguageUsage => "Используйте формат <code>/language en|ru</code>.",
+        Text::PunishmentEndUsage => {
+            "Используйте формат <code>/punishmentend chat|private|off</code>."
+        }
+        Text::TemplatesList => {
+            "Шаблоны можно переопределить для чата, группы предупреждений или типа предупреждения. \
+             Доступные ключи:\n{keys}"
+        }
+        Text::TemplateEmpty => "Текст шаблона пуст.",
+        Text::TemplateInvalidHtml => {
+            "Шаблон не является корректным HTML. Закройте все теги и пишите &lt; и &amp; как \
+             <code>&amp;lt;</code> и <code>&amp;amp;</code>."
+        }
+        Text::TemplateSet => "Шаблон установлен. Пример:\n{preview}",
+        Text::TemplateReset => "Шаблон по умолчанию восстановлен.",
+        Text::NoSuchTemplate => "Такого шаблона нет. Смотрите /templates.",
+        Text::NoSuchScope => "Такого типа или группы предупреждений нет: {scope}.",
+
+        Text::ImportReplyToDocument => "Ответьте командой /importconfig на документ с настройками.",
+        Text::ImportTooLarge => "Документ слишком большой для файла настроек.",
+        Text::ImportInvalid => "Настройки содержат ошибки:\n<pre>{errors}</pre>",
+        Text::ImportNoChanges => "Настройки совпадают с текущими, импортировать нечего.",
+        Text::ImportDiff => {
+            "Импорт внесёт следующие изменения:\n<pre>{changes}</pre>\nПрименить их?"
+        }
+        Text::ApplyButton => "Применить",
+        Text::CancelButton => "Отменить",
+        Text::ImportApplied => "Настройки импортированы.",
+        Text::ImportCancelled => "Импорт отменён.",
+        Text::ImportConflict => {
+            "Во время импорта был создан тип предупреждения или группа. Проверьте настройки и \
+             импортируйте их снова."
+        }
+        Text::ErrorStorage => {
+            "База данных сейчас недоступна, попробуйте позже. Код ошибки: <code>{id}</code>."
+        }
+        Text::ErrorRights => {
+            "Боту не хватает прав администратора для этого. Код ошибки: <code>{id}</code>."
+        }
+        Text::ErrorInternal => "Что-то пошло не так. Код ошибки: <code>{id}</code>.",
+    }
+}
+
+pub fn template(key: TemplateKey) -> &'static str {
+    match key {
+        TemplateKey::Warned => "{user} получает предупреждение! {points}/{max} баллов.\n{reason}",
+        TemplateKey::Punished => "{user} {punishment} {until}!\n{reason}",
+        TemplateKey::PunishFailed => {
+            "{user} набирает {points}/{max} баллов, но мне не удалось наказать пользователя: \
+             {error}. Предупреждения сохранены."
+        }
+        TemplateKey::PunishmentEnded => "{user} больше не {punishment}.",
+        TemplateKey::SetupAlreadyStarted => "Вы уже создаёте новый тип предупреждения.",
+        TemplateKey::SetupAskGroup => {
+            "Хорошо. Отправьте мне название группы, к которой относится предупреждение."
+        }
+        TemplateKey::SetupCancelled => "Отменено.",
+        TemplateKey::SetupNoSuchGroup => {
+            "Такой группы предупреждений нет. Отправьте мне название группы, к которой относится \
+             предупреждение."
+        }
+        TemplateKey::SetupAskPoints => {
+            "Хорошо. Теперь отправьте количество баллов, которые пользователь получит за это \
+             предупреждение."
+        }
+        TemplateKey::SetupInvalidPoints => {
+            "Отправьте число баллов, которые пользователь получит за это предупреждение."
+        }
+        TemplateKey::SetupAskTrigger => {
+            "Хорошо. Теперь отправьте текстовый триггер предупреждения. Он будет использоваться в \
+             формате <code>/warn &lt;триггер&gt;</code>."
+        }
+        TemplateKey::SetupInvalidTrigger => "Триггер должен состоять из одного слова.",
+        TemplateKey::SetupTriggerExists => "Предупреждение с таким триггером уже существует.",
+        TemplateKey::SetupAskOnWarn => {
+            "Хорошо. Удалять сообщение, на которое отвечают при выдаче предупреждения?"
+        }
+        TemplateKey::SetupUseButtons => "Пожалуйста, воспользуйтесь кнопками выше.",
+        TemplateKey::SetupDone => {
+            "Новый тип предупреждения добавлен. Чтобы его использовать, отправьте команду \
+             <code>/warn {trigger}</code>."
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Jobs that must run at a given time, even if the bot was restarted in the
+//! meantime. Jobs are stored in the database and run by a background worker.
+//!
+//! A job is leased while it runs and retried with a backoff if it fails, so it
+//! runs at least once. Jobs must be safe to run more than once.
+
+use crate::{
+    cleanup::CleanupQueue,
+    metrics,
+    settings::SettingsRepository,
+    warnings::{self, WarnsRepository},
+    TBot,
+};
+use chrono::{DateTime, Utc};
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Document},
+    options::{FindOneAndUpdateOptions, ReturnDocument},
+    Collection, Database, IndexModel,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use teloxide::prelude2::*;
+
+/// How often the worker looks for due jobs when there are none.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How long a job is reserved for the worker that took it. If the worker dies,
+/// the job is taken again after the lease expires.
+const LEASE_SECS: i64 = 60;
+/// How many times a job is tried before it is marked as failed.
+const MAX_ATTEMPTS: u32 = 5;
+/// The delay before the first retry. It doubles with every attempt.
+const RETRY_DELAY_SECS: i64 = 30;
+
+/// What to do when the time of a job comes.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Job {
+    DeleteMessage {
+        chat_id: i64,
+        message_id: i32,
+    },
+    /// A temporary punishment of the user may have ended.
+    PunishmentEnded {
+        chat_id: i64,
+        user_id: i64,
+    },
+}
+
+/// What jobs may need to run.
+#[derive(Clone)]
+pub struct JobContext {
+    pub bot: TBot,
+    pub repo: WarnsRepository,
+    pub settings_repo: SettingsRepository,
+    pub cleanup: CleanupQueue,
+}
+
+impl Job {
+    async fn run(&self, ctx: &JobContext) -> anyhow::Result<()> {
+        match self {
+            Job::DeleteMessage { chat_id, message_id } => {
+                // The message may be already deleted by someone else, so there is no reason
+                // to retry.
+                if let Err(err) = ctx.bot.delete_message(*chat_id, *message_id).await {
+                    log::warn!("Failed to delete message {}: {}", message_id, err);
+                    metrics::telegram_error("delete_message");
+                }
+            }
+            Job::PunishmentEnded { chat_id, user_id } => {
+                warnings::punishment_ended(ctx, *chat_id, *user_id).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+struct StoredJob {
+    #[serde(rename = "_id")]
+    id: ObjectId,
+    job: Job,
+    /// Unix timestamp in seconds.
+    run_at: i64,
+    attempts: u32,
+    /// Unix timestamp in seconds until which the job is leased by a worker.
+    #[serde(default)]
+    locked_until: Option<i64>,
+    #[serde(default)]
+    failed: bool,
+    #[serde(default)]
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    jobs: Collection<StoredJob>,
+}
+
+impl Scheduler {
+    pub fn new(db: &Database) -> Self {
+        Self { jobs: db.collection("jobs") }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        let index = IndexModel::builder().keys(doc! { "failed": 1, "run_at": 1 }).build();
+        self.jobs.create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Schedules the job to run after `delay_secs` seconds.
+    pub async fn enqueue_in(&self, job: Job, delay_secs: u64) -> Result<(), mongodb::error::Error> {
+        self.enqueue_at(job, Utc::now() + chrono::Duration::seconds(delay_secs as i64)).await
+    }
+
+    pub async fn enqueue_at(
+        &self,
+        job: Job,
+        run_at: DateTime<Utc>,
+    ) -> Result<(), mongodb::error::Error> {
+        let job = StoredJob {
+            id: ObjectId::new(),
+            job,
+            run_at: run_at.timestamp(),
+            attempts: 0,
+            locked_until: None,
+            failed: false,
+            last_error: None,
+        };
+        self.jobs.insert_one(job, None).await?;
+        Ok(())
+    }
+
+    /// Runs due jobs. Runs forever.
+    pub async fn run(self, ctx: JobContext) {
+        loop {
+            match self.run_next(&ctx).await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(err) => log::error!("Failed to run scheduled jobs: {}", err),
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Runs the next due job, if any. Returns `false` if there are no due jobs.
+    async fn run_next(&self, ctx: &JobContext) -> Result<bool, mongodb::error::Error> {
+        let document = match self.lease_next().await? {
+            Some(document) => document,
+            None => return Ok(false),
+        };
+        // Retrying does not help if the job cannot be read, e.g. it was stored by
+        // another version of the bot.
+        let job: StoredJob = match bson::from_document(document.clone()) {
+            Ok(job) => job,
+            Err(err) => {
+                log::error!("Cannot read job {}: {}", document, err);
+                let filter = doc! { "_id": document.get("_id").cloned() };
+                self.mark_failed(filter, format!("cannot read the job: {}", err)).await?;
+                return Ok(true);
+            }
+        };
+        // Leases of the job expired without an outcome, e.g. it crashes the worker.
+        if job.attempts > MAX_ATTEMPTS {
+            log::error!("Job {:?} was not finished in {} attempts", job.job, MAX_ATTEMPTS);
+            let error = format!("not finished in {} attempts", MAX_ATTEMPTS);
+            self.mark_failed(doc! { "_id": job.id }, error).await?;
+            return Ok(true);
+        }
+        match job.job.run(ctx).await {
+            Ok(()) => {
+                self.jobs.delete_one(doc! { "_id": job.id }, None).await?;
+            }
+            Err(err) if job.attempts >= MAX_ATTEMPTS => {
+                log::error!("Job {:?} failed after {} attempts: {}", job.job, job.attempts, err);
+                self.mark_failed(doc! { "_id": job.id }, err.to_string()).await?;
+            }
+            Err(err) => {
+                log::warn!("Job {:?} failed, will retry: {}", job.job, err);
+                let delay = RETRY_DELAY_SECS << (job.attempts - 1);
+                self.jobs
+                    .update_one(
+                        doc! { "_id": job.id },
+                        doc! {
+                            "$set": {
+                                "run_at": Utc::now().timestamp() + delay,
+                                "last_error": err.to_string(),
+                            },
+                            "$unset": { "locked_until": "" },
+                        },
+                        None,
+                    )
+                    .await?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Stops retrying the job. Failed jobs are kept for inspection.
+    async fn mark_failed(
+        &self,
+        filter: Document,
+        error: String,
+    ) -> Result<(), mongodb::error::Error> {
+        let update = doc! { "$set": { "failed": true, "last_error": error } };
+        self.jobs.update_one(filter, update, None).await?;
+        Ok(())
+    }
+
+    /// Takes the earliest due job which is not leased by another worker. The
+    /// job is returned as a document, so jobs which cannot be read are leased as
+    /// well and can be marked as failed.
+    async fn lease_next(&self) -> Result<Option<Document>, mongodb::error::Error> {
+        let now = Utc::now().timestamp();
+        let options = FindOneAndUpdateOptions::builder()
+            .sort(doc! { "run_at": 1 })
+            .return_document(ReturnDocument::After)
+            .build();
+        self.jobs
+            .clone_with_type::<Document>()
+            .find_one_and_update(
+                doc! {
+                    "failed": false,
+                    "run_at": { "$lte": now },
+                    "locked_until": { "$not": { "$gt": now } },
+                },
+                doc! {
+                    "$set": { "locked_until": now + LEASE_SECS },
+                    "$inc": { "attempts": 1 },
+                },
+                options,
+            )
+            .await
+    }
+}
@@ -1,37 +1,146 @@
+pub mod admin;
+mod app_config;
+mod cleanup;
+mod config;
+mod errors;
+mod i18n;
+mod jobs;
+mod metrics;
+mod migrations;
+mod recording;
+pub mod replay;
+mod settings;
 mod simple_commands;
+mod status;
+mod templates;
 mod utils;
 mod warnings;
+pub mod webhook;
 
 use crate::{
+    cleanup::CleanupQueue,
+    config::{setup_config_callback_queries_handler, setup_config_handler, Config, PendingImports},
+    errors::{self, ErrorReporter},
+    jobs::{JobContext, Scheduler},
+    recording::Recorder,
+    settings::{setup_settings_handler, SettingsRepository},
     simple_commands::setup_simple_commands,
-    warnings::{setup_warnings_callback_queries_handler, setup_warnings_handler, WarnsRepository},
+    warnings::{
+        setup_warnings_callback_queries_handler, setup_warnings_handler, PendingAnonymousCommands,
+        WarnsRepository,
+    },
 };
+use anyhow::Context;
 use mongodb::Database;
+use std::sync::Arc;
 use teloxide::{
-    adaptors::{trace::Settings, Trace},
+    adaptors::{throttle::Limits, trace::Settings, Throttle, Trace},
     dispatching2::{dialogue::InMemStorage, UpdateHandler},
     prelude2::*,
 };
 
-pub type TBot = AutoSend<Trace<Bot>>;
+pub type TBot = AutoSend<Trace<Throttle<Bot>>>;
 pub type HandlerOut = Result<(), anyhow::Error>;
 pub type Handler = UpdateHandler<anyhow::Error>;
-pub const BOT_NAME: &'static str = "autowarn_bot";
 
-pub async fn setup_dispatcher(bot: Bot, db: Database) -> Dispatcher<TBot, anyhow::Error> {
-    use teloxide::prelude2::*;
+pub use app_config::{AppConfig, DatabaseConfig, WebhookConfig};
+pub use status::{DispatcherState, Health};
 
-    let bot = bot.trace(Settings::all()).auto_send();
+pub async fn setup_dispatcher(
+    bot: Bot,
+    db: Database,
+    app_config: &AppConfig,
+    health: Arc<Health>,
+) -> anyhow::Result<Dispatcher<TBot, anyhow::Error>> {
+    metrics::init();
+    let recorder = match &app_config.record {
+        Some(path) => Some(Arc::new(Recorder::open(path).context("cannot open the recording")?)),
+        None => None,
+    };
+    let bot = match &recorder {
+        Some(recorder) => {
+            let proxy = recording::start_proxy(recorder.clone(), bot.api_url());
+            bot.set_api_url(proxy)
+        }
+        None => bot,
+    };
+    // Telegram limits how many messages the bot may send to a chat and overall, so
+    // messages are queued instead of failing during raids.
+    let bot = bot.throttle(Limits::default()).trace(Settings::all()).auto_send();
     let repo = WarnsRepository::new(&db);
+    let settings_repo = SettingsRepository::new(&db);
+    let scheduler = Scheduler::new(&db);
+    let cleanup = CleanupQueue::new(scheduler.clone());
     let storage = InMemStorage::new();
-    repo.insert_default_values().await.unwrap();
+    let edit_storage = InMemStorage::new();
+    let pending = Arc::new(PendingAnonymousCommands::default());
+    let pending_imports = Arc::new(PendingImports::default());
+    // Started first, so the orchestrator sees that the bot is starting.
+    if let Some(addr) = app_config.status_addr {
+        let server = status::serve(addr, health.clone(), db.clone())
+            .with_context(|| format!("cannot serve the status on {}", addr))?;
+        tokio::spawn(server);
+    }
+    migrations::run(&db, app_config.legacy_chat).await.context("cannot migrate the database")?;
+    repo.ensure_indexes().await.context("cannot create indexes")?;
+    settings_repo.ensure_indexes().await.context("cannot create indexes")?;
+    scheduler.ensure_indexes().await.context("cannot create indexes")?;
+    if let Some(path) = &app_config.chats_config {
+        let config = Config::load(path)?;
+        let report = config::sync(&config, &repo, &settings_repo)
+            .await
+            .context("cannot sync the configuration")?;
+        log::info!("Synced the configuration: {}", report);
+    }
+    let job_context = JobContext {
+        bot: bot.clone(),
+        repo: repo.clone(),
+        settings_repo: settings_repo.clone(),
+        cleanup: cleanup.clone(),
+    };
+    tokio::spawn(scheduler.clone().run(job_context));
+    i18n::register_commands(&bot).await;
+    // Commands may be addressed to the bot by its username, which differs between
+    // instances of the bot. Handlers get the bot's user from the dependencies.
+    let me = bot.get_me().await.context("cannot get the bot's user")?;
+    let bot_name =
+        me.user.username.clone().ok_or_else(|| anyhow::anyhow!("the bot has no username"))?;
 
-    Dispatcher::new(bot.clone())
-        .dependencies(dptree::deps![repo, db])
+    let dispatcher = Dispatcher::new(bot.clone())
+        .error_handler(Arc::new(ErrorReporter::new(
+            bot.clone(),
+            settings_repo.clone(),
+            app_config.operators.clone(),
+        )))
+        .dependencies(dptree::deps![repo, settings_repo, cleanup, scheduler, db, me])
         .messages_handler(|h| {
-            h.branch(setup_warnings_handler(storage.clone())).branch(setup_simple_commands())
+            h.branch(
+                status::track_updates(health.clone())
+                    .chain(recording::record_updates(recorder.clone()))
+                    .chain(errors::message_errors())
+                    .branch(setup_warnings_handler(
+                        &bot_name,
+                        storage.clone(),
+                        edit_storage.clone(),
+                        pending.clone(),
+                    ))
+                    .branch(setup_settings_handler(&bot_name))
+                    .branch(setup_config_handler(&bot_name, pending_imports.clone()))
+                    .branch(setup_simple_commands(&bot_name)),
+            )
         })
         .callback_queries_handler(|h| {
-            h.branch(setup_warnings_callback_queries_handler(storage.clone()))
-        })
+            h.branch(
+                status::track_updates(health.clone())
+                    .chain(recording::record_updates(recorder.clone()))
+                    .chain(errors::callback_errors())
+                    .branch(setup_config_callback_queries_handler(pending_imports.clone()))
+                    .branch(setup_warnings_callback_queries_handler(
+                        storage.clone(),
+                        edit_storage.clone(),
+                        pending.clone(),
+                    )),
+            )
+        });
+    Ok(dispatcher)
 }
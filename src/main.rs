@@ -1,29 +1,78 @@
-use mongodb::{options::ClientOptions, Client, Database};
-use roff_bot::setup_dispatcher;
+use anyhow::Context;
+use mongodb::{bson::doc, options::ClientOptions, Client, Database};
+use roff_bot::{setup_dispatcher, webhook, AppConfig, DispatcherState, Health};
+use std::{sync::Arc, time::Duration};
 use teloxide::prelude2::*;
 
+/// How many times to try to reach MongoDB before giving up.
+const MONGO_ATTEMPTS: u32 = 8;
+/// The delay before the first retry. It doubles with every attempt.
+const MONGO_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MONGO_MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     // TODO: maybe bot was shutdown because of network problems so we want to check
     // that we punish all user we might to, and if not so punish them.
 
     teloxide::enable_logging!();
     log::info!("Starting bot...");
 
-    let bot = Bot::from_env();
-    let db = connect_to_mongo().await;
+    let config = AppConfig::from_env().context("invalid configuration")?;
+    let bot = Bot::new(&config.token);
+    let db = connect_to_mongo(&config).await?;
 
-    setup_dispatcher(bot, db).await.setup_ctrlc_handler().dispatch().await;
-}
+    let health = Arc::new(Health::default());
+    let mut dispatcher =
+        setup_dispatcher(bot.clone(), db, &config, health.clone()).await?.setup_ctrlc_handler();
+    health.set_dispatcher(DispatcherState::Running);
+    match &config.webhook {
+        Some(webhook_config) => {
+            let listener = webhook::listener(&bot, webhook_config)
+                .await
+                .context("cannot set up the webhook")?;
+            let error_handler =
+                LoggingErrorHandler::with_custom_text("An error from the update listener");
+            dispatcher.dispatch_with_listener(listener, error_handler).await;
+        }
+        None => dispatcher.dispatch().await,
+    }
+    health.set_dispatcher(DispatcherState::Stopped);
 
-async fn connect_to_mongo() -> Database {
-    let env = std::env::var("MONGO_OPTIONS").expect("You must define MONGO_OPTIONS env variable.");
+    Ok(())
+}
 
+/// Connects to MongoDB, retrying with a backoff while it is not reachable, e.g.
+/// when it is started together with the bot.
+async fn connect_to_mongo(config: &AppConfig) -> anyhow::Result<Database> {
     log::info!("Connecting to the database...");
-    let options =
-        ClientOptions::parse(env).await.expect("Fail to parse MONGO_OPTIONS env variable.");
-    let client = Client::with_options(options).expect("Cannot connect to the mongodb.");
+    let options = ClientOptions::parse(&config.mongo_options)
+        .await
+        .context("invalid MONGO_OPTIONS env variable")?;
+    let client = Client::with_options(options).context("invalid MongoDB client options")?;
+    let db = client.database(&config.database);
+
+    let mut delay = MONGO_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match db.run_command(doc! { "ping": 1 }, None).await {
+            Ok(_) => break,
+            Err(err) if attempt < MONGO_ATTEMPTS => {
+                log::warn!(
+                    "Cannot reach MongoDB (attempt {}/{}), retrying in {:?}: {}",
+                    attempt,
+                    MONGO_ATTEMPTS,
+                    delay,
+                    err
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MONGO_MAX_RETRY_DELAY);
+                attempt += 1;
+            }
+            Err(err) => return Err(err).context("cannot reach MongoDB"),
+        }
+    }
     log::info!("Connected!");
 
-    client.database("database")
+    Ok(db)
 }
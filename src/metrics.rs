@@ -0,0 +1,103 @@
+//! Prometheus metrics of the bot. They are served by the status server, see
+//! [`crate::status`].
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounterVec, Opts, Registry,
+    TextEncoder,
+};
+
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+fn counter(name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels).unwrap();
+    REGISTRY.register(Box::new(counter.clone())).unwrap();
+    counter
+}
+
+/// Warns given, by group and trigger.
+pub static WARNS: Lazy<IntCounterVec> =
+    Lazy::new(|| counter("autowarn_warns_total", "Warns given.", &["group", "trigger"]));
+
+/// Punishments given, by kind.
+pub static PUNISHMENTS: Lazy<IntCounterVec> =
+    Lazy::new(|| counter("autowarn_punishments_total", "Punishments given.", &["kind"]));
+
+/// Messages that matched an auto trigger, by the trigger of the warn type.
+pub static AUTO_TRIGGER_HITS: Lazy<IntCounterVec> = Lazy::new(|| {
+    counter("autowarn_auto_trigger_hits_total", "Messages matching auto triggers.", &["trigger"])
+});
+
+/// Failed requests to the Telegram Bot API, by what the bot tried to do.
+pub static TELEGRAM_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    counter("autowarn_telegram_errors_total", "Failed Bot API requests.", &["operation"])
+});
+
+/// Events of dialogues: `started`, `completed` and `cancelled`.
+pub static DIALOGUES: Lazy<IntCounterVec> =
+    Lazy::new(|| counter("autowarn_dialogues_total", "Dialogue events.", &["dialogue", "event"]));
+
+static HANDLER_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    let opts = HistogramOpts::new("autowarn_handler_duration_seconds", "Time to handle updates.");
+    let histogram = HistogramVec::new(opts, &["handler"]).unwrap();
+    REGISTRY.register(Box::new(histogram.clone())).unwrap();
+    histogram
+});
+
+/// Label values known in advance. Their counters are served from the start, so
+/// rates can be computed from zero.
+const PUNISHMENT_KINDS: &[&str] = &["ban", "mute", "restrict", "channel_ban"];
+const TELEGRAM_OPERATIONS: &[&str] = &[
+    "handler",
+    "error_report",
+    "flood_limit",
+    "punish",
+    "delete_message",
+    "delete_command",
+    "punishment_end_notice",
+];
+
+/// Registers all metrics. Call it at startup, otherwise metrics are missing
+/// from [`render`] until they are first used.
+pub fn init() {
+    Lazy::force(&WARNS);
+    Lazy::force(&AUTO_TRIGGER_HITS);
+    Lazy::force(&DIALOGUES);
+    Lazy::force(&HANDLER_DURATION);
+    for kind in PUNISHMENT_KINDS {
+        PUNISHMENTS.with_label_values(&[kind]);
+    }
+    for operation in TELEGRAM_OPERATIONS {
+        TELEGRAM_ERRORS.with_label_values(&[operation]);
+    }
+}
+
+/// Measures the handler until the returned timer is dropped.
+pub fn handler_timer(handler: &str) -> HistogramTimer {
+    HANDLER_DURATION.with_label_values(&[handler]).start_timer()
+}
+
+/// Counts a failed Bot API request. Errors are logged by callers.
+pub fn telegram_error(operation: &str) {
+    TELEGRAM_ERRORS.with_label_values(&[operation]).inc();
+}
+
+/// Renders all metrics in the Prometheus text format.
+pub fn render() -> String {
+    let mut buf = vec![];
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buf).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serves_counters_before_they_are_used() {
+        init();
+        let metrics = render();
+        assert!(metrics.contains(r#"autowarn_punishments_total{kind="mute"} 0"#), "{}", metrics);
+        assert!(metrics.contains(r#"autowarn_telegram_errors_total{operation="punish"} 0"#));
+    }
+}
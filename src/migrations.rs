@@ -0,0 +1,114 @@
+//! Versioning of stored warns, warn types and warn groups.
+//!
+//! Every document of these collections has a `schema_version` field. Documents
+//! written before versioning have no such field and are treated as version 0.
+//! When the shape of a document changes, a migration is added to
+//! [`MIGRATIONS`] and [`SCHEMA_VERSION`] is bumped. Migrations run at startup
+//! in order, and every applied migration is recorded in the
+//! `applied_migrations` collection, so it runs only once.
+
+use crate::warnings::WarnsRepository;
+use anyhow::Context;
+use chrono::Utc;
+use mongodb::{
+    bson::{doc, Document},
+    Collection, Database,
+};
+use serde::{Deserialize, Serialize};
+
+/// The version of documents written by this version of the bot.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Collections whose documents are versioned.
+const COLLECTIONS: [&str; 4] = ["old_warns", "actual_warns", "warning_types", "warning_groups"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Migration {
+    /// Warn types, warns and groups reference each other by ID instead of
+    /// embedding copies.
+    ReferenceGroupsById,
+}
+
+/// All migrations with the version they bring documents to, oldest first.
+const MIGRATIONS: [(u32, Migration); 1] = [(1, Migration::ReferenceGroupsById)];
+
+impl Migration {
+    fn name(self) -> &'static str {
+        match self {
+            Migration::ReferenceGroupsById => "reference_groups_by_id",
+        }
+    }
+
+    async fn run(self, db: &Database, legacy_chat: Option<i64>) -> anyhow::Result<()> {
+        match self {
+            Migration::ReferenceGroupsById => {
+                WarnsRepository::new(db).migrate_embedded_groups(legacy_chat).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+struct AppliedMigration {
+    #[serde(rename = "_id")]
+    version: u32,
+    name: String,
+    /// Unix timestamp in seconds.
+    applied_at: i64,
+}
+
+/// Brings all versioned documents to [`SCHEMA_VERSION`]. Documents stored
+/// before the bot kept them per chat are moved to `legacy_chat`.
+pub async fn run(db: &Database, legacy_chat: Option<i64>) -> anyhow::Result<()> {
+    check_not_newer(db).await?;
+
+    let applied: Collection<AppliedMigration> = db.collection("applied_migrations");
+    for (version, migration) in MIGRATIONS {
+        if applied.find_one(doc! { "_id": version }, None).await?.is_some() {
+            continue;
+        }
+        log::info!("Applying migration {} `{}`", version, migration.name());
+        // Nothing is recorded if the migration fails, so it runs again at the next
+        // start, e.g. once the legacy chat is set.
+        migration
+            .run(db, legacy_chat)
+            .await
+            .with_context(|| format!("migration {} `{}` failed", version, migration.name()))?;
+        for name in COLLECTIONS {
+            db.collection::<Document>(name)
+                .update_many(
+                    doc! { "schema_version": { "$not": { "$gte": version } } },
+                    doc! { "$set": { "schema_version": version } },
+                    None,
+                )
+                .await?;
+        }
+        let record = AppliedMigration {
+            version,
+            name: migration.name().to_string(),
+            applied_at: Utc::now().timestamp(),
+        };
+        applied.insert_one(record, None).await?;
+    }
+    Ok(())
+}
+
+/// Refuses to touch documents written by a newer version of the bot, which this
+/// version may not read correctly.
+async fn check_not_newer(db: &Database) -> anyhow::Result<()> {
+    for name in COLLECTIONS {
+        let newer = db
+            .collection::<Document>(name)
+            .find_one(doc! { "schema_version": { "$gt": SCHEMA_VERSION } }, None)
+            .await?;
+        if newer.is_some() {
+            anyhow::bail!(
+                "`{}` has documents of a newer schema than {}; update the bot",
+                name,
+                SCHEMA_VERSION
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,158 @@
+//! Recording of updates and Bot API calls for reproducing incidents, see
+//! [`crate::replay`].
+//!
+//! If the `AUTOWARN_RECORD` env variable is set, every update the bot handles
+//! and every request it makes are appended to the file as JSON lines. Requests
+//! are recorded by a local proxy the bot sends them to, which forwards them to
+//! the Bot API. The token of the bot is not recorded, but updates contain
+//! messages of users, so recordings must be treated as private data.
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs::{File, OpenOptions},
+    io::{LineWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use teloxide::{prelude2::*, types::Update};
+use warp::{
+    http::{HeaderMap, Method, Response},
+    hyper::body::Bytes,
+    path::FullPath,
+    Filter,
+};
+
+/// Calls made repeatedly regardless of updates, or only with long polling.
+const NOT_RECORDED: [&str; 2] = ["GetUpdates", "DeleteWebhook"];
+
+/// A line of a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Record {
+    Update(Value),
+    Call(Call),
+}
+
+/// A Bot API request and what the Bot API answered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Call {
+    pub method: String,
+    /// `null` for requests which are not JSON, e.g. file uploads.
+    pub params: Value,
+    pub response: Value,
+}
+
+pub struct Recorder {
+    file: Mutex<LineWriter<File>>,
+}
+
+impl Recorder {
+    /// Opens the file for appending, so restarts do not erase the recording.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file: Mutex::new(LineWriter::new(file)) })
+    }
+
+    fn write(&self, record: &Record) {
+        let mut line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(err) => {
+                log::error!("Cannot serialize a record: {}", err);
+                return;
+            }
+        };
+        line.push('\n');
+        if let Err(err) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            log::error!("Cannot write a record: {}", err);
+        }
+    }
+}
+
+/// Passes all updates, recording them if there is a recorder.
+pub fn record_updates(recorder: Option<Arc<Recorder>>) -> crate::Handler {
+    dptree::filter(move |update: Update| {
+        if let Some(recorder) = &recorder {
+            match serde_json::to_value(&update) {
+                Ok(update) => recorder.write(&Record::Update(update)),
+                Err(err) => log::error!("Cannot serialize update {}: {}", update.id, err),
+            }
+        }
+        async { true }
+    })
+}
+
+/// Starts a proxy which forwards requests to `upstream` and records them.
+/// Returns the URL of the proxy to use as the API URL of the bot.
+pub fn start_proxy(recorder: Arc<Recorder>, upstream: Url) -> Url {
+    let client = reqwest::Client::new();
+    let route = warp::method()
+        .and(warp::path::full())
+        .and(warp::header::headers_cloned())
+        .and(warp::body::bytes())
+        .and_then(move |method: Method, path: FullPath, headers: HeaderMap, body: Bytes| {
+            let request = Forward { method, path, headers, body };
+            forward(client.clone(), upstream.clone(), recorder.clone(), request)
+        });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    log::info!("Recording Bot API calls through {}", addr);
+    Url::parse(&format!("http://{}", addr)).unwrap()
+}
+
+struct Forward {
+    method: Method,
+    path: FullPath,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+async fn forward(
+    client: reqwest::Client,
+    upstream: Url,
+    recorder: Arc<Recorder>,
+    request: Forward,
+) -> Result<Response<Bytes>, warp::Rejection> {
+    let failed = |err: reqwest::Error| {
+        log::error!("Cannot forward a Bot API request: {}", err);
+        Response::builder().status(502).body(Bytes::new()).unwrap()
+    };
+    let url = match upstream.join(request.path.as_str()) {
+        Ok(url) => url,
+        Err(_) => return Ok(Response::builder().status(400).body(Bytes::new()).unwrap()),
+    };
+    let method = reqwest::Method::from_bytes(request.method.as_str().as_bytes()).unwrap();
+    let mut upstream_request = client.request(method, url).body(request.body.clone());
+    if let Some(content_type) = request.headers.get("content-type") {
+        upstream_request = upstream_request.header("content-type", content_type.as_bytes());
+    }
+    let response = match upstream_request.send().await {
+        Ok(response) => response,
+        Err(err) => return Ok(failed(err)),
+    };
+    let status = response.status().as_u16();
+    let content_type = response.headers().get("content-type").cloned();
+    let body = match response.bytes().await {
+        Ok(body) => body,
+        Err(err) => return Ok(failed(err)),
+    };
+
+    // Bot API paths are `/bot<token>/<method>`, files are under `/file`.
+    let segments: Vec<_> = request.path.as_str().trim_start_matches('/').split('/').collect();
+    if let [bot, method] = segments[..] {
+        if bot.starts_with("bot") && !NOT_RECORDED.iter().any(|m| m.eq_ignore_ascii_case(method)) {
+            recorder.write(&Record::Call(Call {
+                method: method.to_string(),
+                params: serde_json::from_slice(&request.body).unwrap_or(Value::Null),
+                response: serde_json::from_slice(&body).unwrap_or(Value::Null),
+            }));
+        }
+    }
+
+    let mut reply = Response::builder().status(status);
+    if let Some(content_type) = content_type {
+        reply = reply.header("content-type", content_type.as_bytes());
+    }
+    Ok(reply.body(body).unwrap())
+}
@@ -0,0 +1,230 @@
+//! Replaying of recordings made with `AUTOWARN_RECORD`, see
+//! [`crate::recording`].
+//!
+//! The recorded updates are fed through the dispatcher set up by
+//! [`setup_dispatcher`] as usual, but the bot talks to a fake Bot API which
+//! answers with the recorded responses. The calls the bot makes are compared to
+//! the recorded ones. Data is stored in a scratch database, which is dropped
+//! afterwards, and dialogues are kept in memory as usual, so only the chats
+//! configuration given to the replay is known to the bot at the start.
+//!
+//! The replay needs a running MongoDB. Replaying against in-memory storage
+//! needs a storage trait over the repositories and is not done yet.
+
+use crate::{
+    recording::{Call, Record},
+    setup_dispatcher, AppConfig, Health,
+};
+use anyhow::Context;
+use mongodb::{bson::oid::ObjectId, Client};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use teloxide::{
+    dispatching::{stop_token::AsyncStopToken, update_listeners::StatefulListener},
+    prelude2::*,
+    types::Update,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use warp::{hyper::body::Bytes, Filter};
+
+/// The replay is over when the bot makes no calls for this long after the last
+/// update is handled, e.g. finishes scheduled jobs.
+const QUIET_TIME: Duration = Duration::from_secs(2);
+
+pub struct ReplayOptions {
+    pub recording: PathBuf,
+    /// Connection string of the MongoDB server for the scratch database.
+    pub mongo_options: String,
+    pub chats_config: Option<PathBuf>,
+}
+
+/// Calls which differ between the recording and the replay. Updates of
+/// different chats are handled concurrently, so the order of calls is ignored.
+#[derive(Debug, Default)]
+pub struct Diff {
+    /// Recorded calls the bot did not make.
+    pub missing: Vec<String>,
+    /// Calls the bot made which were not recorded.
+    pub extra: Vec<String>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty()
+    }
+
+    fn new(recorded: &[Call], replayed: &[Call]) -> Self {
+        let mut extra: Vec<_> = replayed.iter().map(call_line).collect();
+        let mut missing = vec![];
+        for line in recorded.iter().map(call_line) {
+            match extra.iter().position(|other| *other == line) {
+                Some(i) => {
+                    extra.remove(i);
+                }
+                None => missing.push(line),
+            }
+        }
+        Self { missing, extra }
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.missing {
+            writeln!(f, "- {}", line)?;
+        }
+        for line in &self.extra {
+            writeln!(f, "+ {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+fn call_line(call: &Call) -> String {
+    format!("{} {}", call.method, call.params)
+}
+
+/// Reads a recording, skipping lines which cannot be parsed, e.g. the last one
+/// if the bot was killed while writing it.
+fn read(path: &Path) -> anyhow::Result<Vec<Record>> {
+    let file = File::open(path).with_context(|| format!("cannot open {}", path.display()))?;
+    let mut records = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        match serde_json::from_str(&line?) {
+            Ok(record) => records.push(record),
+            Err(err) => log::warn!("Skipping line {} of the recording: {}", i + 1, err),
+        }
+    }
+    Ok(records)
+}
+
+pub async fn run(options: &ReplayOptions) -> anyhow::Result<Diff> {
+    let records = read(&options.recording)?;
+    let mut updates = vec![];
+    let mut recorded = vec![];
+    for record in records {
+        match record {
+            Record::Update(update) => updates.push(
+                serde_json::from_value::<Update>(update)
+                    .context("cannot parse a recorded update")?,
+            ),
+            Record::Call(call) => recorded.push(call),
+        }
+    }
+    log::info!("Replaying {} updates and {} calls", updates.len(), recorded.len());
+
+    let api = FakeApi::start(&recorded);
+    let config = AppConfig {
+        token: "0:replay".to_string(),
+        mongo_options: options.mongo_options.clone(),
+        database: format!("autowarn_replay_{}", ObjectId::new()),
+        legacy_chat: None,
+        chats_config: options.chats_config.clone(),
+        operators: vec![],
+        status_addr: None,
+        record: None,
+        webhook: None,
+    };
+    let client =
+        Client::with_uri_str(&config.mongo_options).await.context("cannot reach MongoDB")?;
+    let db = client.database(&config.database);
+    let result = replay(&api, &config, db.clone(), updates).await;
+    db.drop(None).await.context("cannot drop the scratch database")?;
+    result?;
+
+    let replayed = api.calls.lock().unwrap().clone();
+    Ok(Diff::new(&recorded, &replayed))
+}
+
+async fn replay(
+    api: &FakeApi,
+    config: &AppConfig,
+    db: mongodb::Database,
+    updates: Vec<Update>,
+) -> anyhow::Result<()> {
+    let bot = Bot::new(&config.token).set_api_url(api.url.clone());
+    let mut dispatcher = setup_dispatcher(bot, db, config, Arc::new(Health::default())).await?;
+
+    let (tx, rx) = mpsc::unbounded_channel::<Result<Update, Infallible>>();
+    for update in updates {
+        tx.send(Ok(update)).unwrap();
+    }
+    // The listener stops when all updates are taken.
+    drop(tx);
+    let (stop_token, _) = AsyncStopToken::new_pair();
+    fn stream_of<S, T>(state: &mut (S, T)) -> &mut S {
+        &mut state.0
+    }
+    let listener = StatefulListener::new(
+        (UnboundedReceiverStream::new(rx), stop_token),
+        stream_of,
+        |state: &mut (_, AsyncStopToken)| state.1.clone(),
+    );
+    let error_handler = LoggingErrorHandler::with_custom_text("An error from the replay listener");
+    dispatcher.dispatch_with_listener(listener, error_handler).await;
+
+    loop {
+        let calls = api.calls.lock().unwrap().len();
+        tokio::time::sleep(QUIET_TIME).await;
+        if api.calls.lock().unwrap().len() == calls {
+            return Ok(());
+        }
+    }
+}
+
+/// Answers calls with the responses recorded for the same method, in order.
+struct FakeApi {
+    url: reqwest::Url,
+    calls: Arc<Mutex<Vec<Call>>>,
+}
+
+impl FakeApi {
+    fn start(recorded: &[Call]) -> Self {
+        let mut responses: HashMap<String, VecDeque<Value>> = HashMap::new();
+        for call in recorded {
+            let queue = responses.entry(call.method.to_lowercase()).or_default();
+            queue.push_back(call.response.clone());
+        }
+        let responses = Arc::new(Mutex::new(responses));
+        let calls = Arc::new(Mutex::new(vec![]));
+        let route = {
+            let calls = calls.clone();
+            warp::post()
+                .and(warp::path::param::<String>())
+                .and(warp::path::param::<String>())
+                .and(warp::path::end())
+                .and(warp::body::bytes())
+                .map(move |_token: String, method: String, body: Bytes| {
+                    let recorded = responses
+                        .lock()
+                        .unwrap()
+                        .get_mut(&method.to_lowercase())
+                        .and_then(VecDeque::pop_front);
+                    let response = recorded.unwrap_or_else(|| {
+                        json!({
+                            "ok": false,
+                            "error_code": 400,
+                            "description": format!("Bad Request: {} was not recorded", method),
+                        })
+                    });
+                    let params = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                    let call = Call { method, params, response: response.clone() };
+                    calls.lock().unwrap().push(call);
+                    warp::reply::json(&response)
+                })
+        };
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        Self { url: reqwest::Url::parse(&format!("http://{}", addr)).unwrap(), calls }
+    }
+}
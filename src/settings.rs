@@ -0,0 +1,10 @@
+//! This module about per-chat settings of the bot
+
+pub(crate) mod commands;
+mod dto;
+mod handlers;
+mod repository;
+
+pub use dto::{ChatSettings, PunishmentEndNotice};
+pub use handlers::setup_settings_handler;
+pub use repository::SettingsRepository;
@@ -0,0 +1,61 @@
+use teloxide::utils::command::{BotCommand, ParseError};
+
+#[derive(Debug, Clone, BotCommand)]
+#[command(rename = "lowercase")]
+pub enum SettingsCommand {
+    Settings,
+    DeleteCommands {
+        value: String,
+    },
+    AnnouncementTtl {
+        value: String,
+    },
+    Language {
+        value: String,
+    },
+    PunishmentEnd {
+        value: String,
+    },
+    Templates,
+    #[command(parse_with = "parse_set_template_args")]
+    SetTemplate {
+        key: String,
+        scope: String,
+        text: String,
+    },
+    #[command(parse_with = "parse_reset_template_args")]
+    ResetTemplate {
+        key: String,
+        scope: String,
+    },
+}
+
+/// Splits off the first word of `input`, keeping line breaks of the rest.
+fn split_word(input: &str) -> (&str, &str) {
+    let input = input.trim_start();
+    match input.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim_start()),
+        None => (input, ""),
+    }
+}
+
+fn split_scope(input: &str) -> (&str, &str) {
+    let (word, rest) = split_word(input);
+    if word.starts_with("trigger=") || word.starts_with("group=") {
+        (word, rest)
+    } else {
+        ("", input)
+    }
+}
+
+fn parse_set_template_args(input: String) -> Result<(String, String, String), ParseError> {
+    let (key, rest) = split_word(&input);
+    let (scope, text) = split_scope(rest);
+    Ok((key.to_string(), scope.to_string(), text.trim_end().to_string()))
+}
+
+fn parse_reset_template_args(input: String) -> Result<(String, String), ParseError> {
+    let (key, rest) = split_word(&input);
+    let (scope, _) = split_scope(rest);
+    Ok((key.to_string(), scope.to_string()))
+}
@@ -0,0 +1,57 @@
+use crate::{i18n::Lang, templates::Templates};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatSettings {
+    pub chat_id: i64,
+    /// Delete `/warn` commands right after they are handled.
+    pub delete_commands: bool,
+    /// Seconds after which the bot deletes its announcements. `None` means
+    /// announcements are never deleted.
+    pub announcement_ttl: Option<u64>,
+    /// Templates overridden for the chat.
+    pub templates: Templates,
+    /// `None` means the language was not chosen.
+    pub language: Option<Lang>,
+    /// Where to announce that a temporary punishment has ended.
+    pub punishment_end_notice: PunishmentEndNotice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PunishmentEndNotice {
+    /// In the chat where the user was punished.
+    Chat,
+    /// In private messages to the punished user.
+    Private,
+    Off,
+}
+
+impl Default for PunishmentEndNotice {
+    fn default() -> Self {
+        Self::Chat
+    }
+}
+
+impl PunishmentEndNotice {
+    pub const ALL: &'static [PunishmentEndNotice] = &[Self::Chat, Self::Private, Self::Off];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Chat => "chat",
+            Self::Private => "private",
+            Self::Off => "off",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|notice| notice.code() == code)
+    }
+}
+
+impl ChatSettings {
+    pub fn lang(&self) -> Lang {
+        self.language.unwrap_or_default()
+    }
+}
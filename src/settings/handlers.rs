@@ -0,0 +1,177 @@
+use crate::{
+    errors::UserInputError,
+    i18n::{self, Lang, Text},
+    metrics,
+    settings::{
+        commands::SettingsCommand,
+        dto::{ChatSettings, PunishmentEndNotice},
+        repository::SettingsRepository,
+    },
+    templates::{self, Placeholders, TemplateKey},
+    utils,
+    warnings::WarnsRepository,
+    HandlerOut, TBot,
+};
+use teloxide::{prelude2::*, types::ParseMode, utils::html};
+
+pub fn setup_settings_handler(bot_name: &str) -> crate::Handler {
+    utils::filter_chat_owner()
+        .add_command::<SettingsCommand>(bot_name.into())
+        .endpoint(handle_settings_commands)
+}
+
+async fn handle_settings_commands(
+    bot: TBot,
+    mes: Message,
+    cmd: SettingsCommand,
+    repo: SettingsRepository,
+    warns_repo: WarnsRepository,
+) -> HandlerOut {
+    let _timer = metrics::handler_timer("settings_commands");
+    let chat_id = mes.chat.id;
+    let lang = repo.get(chat_id).await?.lang();
+    let send = |text: Text, placeholders: Placeholders| {
+        bot.send_message(chat_id, i18n::render(lang, text, &placeholders))
+            .parse_mode(ParseMode::Html)
+    };
+    match cmd {
+        SettingsCommand::Settings => {}
+        SettingsCommand::DeleteCommands { value } => {
+            let value = match value.as_str() {
+                "on" => true,
+                "off" => false,
+                _ => return Err(UserInputError(Text::DeleteCommandsUsage).into()),
+            };
+            repo.set_delete_commands(chat_id, value).await?;
+        }
+        SettingsCommand::AnnouncementTtl { value } => {
+            let value = match value.as_str() {
+                "off" => None,
+                secs => match secs.parse::<u64>() {
+                    Ok(secs) => Some(secs),
+                    Err(_) => return Err(UserInputError(Text::AnnouncementTtlUsage).into()),
+                },
+            };
+            repo.set_announcement_ttl(chat_id, value).await?;
+        }
+        SettingsCommand::Language { value } => {
+            let value = match Lang::from_code(&value) {
+                Some(lang) => lang,
+                None => return Err(UserInputError(Text::LanguageUsage).into()),
+            };
+            repo.set_language(chat_id, value).await?;
+        }
+        SettingsCommand::PunishmentEnd { value } => {
+            let value = match PunishmentEndNotice::from_code(&value) {
+                Some(notice) => notice,
+                None => return Err(UserInputError(Text::PunishmentEndUsage).into()),
+            };
+            repo.set_punishment_end_notice(chat_id, value).await?;
+        }
+        SettingsCommand::Templates => {
+            let keys = TemplateKey::ALL
+                .iter()
+                .map(|key| format!("<code>{}</code>", key.name()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            send(Text::TemplatesList, Placeholders::new().html("keys", keys)).await?;
+            return Ok(());
+        }
+        SettingsCommand::SetTemplate { key, scope, text } => {
+            if text.is_empty() {
+                send(Text::TemplateEmpty, Placeholders::new()).await?;
+                return Ok(());
+            }
+            if !templates::is_valid_html(&text) {
+                return Err(UserInputError(Text::TemplateInvalidHtml).into());
+            }
+            if set_template(&bot, &mes, lang, &repo, &warns_repo, &key, &scope, Some(&text))
+                .await?
+                .is_some()
+            {
+                let preview = templates::substitute(&text, &example_placeholders(lang));
+                send(Text::TemplateSet, Placeholders::new().html("preview", preview)).await?;
+            }
+            return Ok(());
+        }
+        SettingsCommand::ResetTemplate { key, scope } => {
+            if set_template(&bot, &mes, lang, &repo, &warns_repo, &key, &scope, None)
+                .await?
+                .is_some()
+            {
+                send(Text::TemplateReset, Placeholders::new()).await?;
+            }
+            return Ok(());
+        }
+    }
+
+    let settings = repo.get(chat_id).await?;
+    send(Text::Settings, settings_placeholders(&settings)).await?;
+
+    Ok(())
+}
+
+fn settings_placeholders(settings: &ChatSettings) -> Placeholders {
+    let lang = settings.lang();
+    let on_off = |value: bool| i18n::text(lang, if value { Text::On } else { Text::Off });
+    let announcement_ttl = match settings.announcement_ttl {
+        Some(secs) => i18n::render(lang, Text::Seconds, &Placeholders::new().text("secs", secs)),
+        None => on_off(false).to_string(),
+    };
+    Placeholders::new()
+        .text("delete_commands", on_off(settings.delete_commands))
+        .html("announcement_ttl", announcement_ttl)
+        .text("language", lang.code())
+        .text("punishment_end", settings.punishment_end_notice.code())
+}
+
+/// Sets the template in the given scope. Returns `None` and replies with an
+/// explanation if the arguments are wrong.
+#[allow(clippy::too_many_arguments)]
+async fn set_template(
+    bot: &TBot,
+    mes: &Message,
+    lang: Lang,
+    repo: &SettingsRepository,
+    warns_repo: &WarnsRepository,
+    key: &str,
+    scope: &str,
+    template: Option<&str>,
+) -> anyhow::Result<Option<TemplateKey>> {
+    let key = match TemplateKey::from_name(key) {
+        Some(key) => key,
+        None => {
+            let text = i18n::render(lang, Text::NoSuchTemplate, &Placeholders::new());
+            bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html).await?;
+            return Ok(None);
+        }
+    };
+    let found = if let Some(trigger) = scope.strip_prefix("trigger=") {
+        warns_repo.set_warn_type_template(mes.chat.id, trigger, key.name(), template).await?
+    } else if let Some(group) = scope.strip_prefix("group=") {
+        warns_repo.set_group_template(mes.chat.id, group, key.name(), template).await?
+    } else {
+        repo.set_template(mes.chat.id, key.name(), template).await?;
+        true
+    };
+    if !found {
+        let placeholders = Placeholders::new().text("scope", scope);
+        let text = i18n::render(lang, Text::NoSuchScope, &placeholders);
+        bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html).await?;
+        return Ok(None);
+    }
+    Ok(Some(key))
+}
+
+fn example_placeholders(lang: Lang) -> Placeholders {
+    Placeholders::new()
+        .html("user", html::bold("Jane Doe"))
+        .text("points", 30)
+        .text("max", 100)
+        .text("group", "spam")
+        .text("trigger", "ad")
+        .text("reason", "advertising")
+        .text("punishment", i18n::text(lang, Text::PunishmentMuted))
+        .text("until", i18n::text(lang, Text::UntilForever))
+        .text("error", "not enough rights")
+}
@@ -0,0 +1,119 @@
+use crate::{
+    i18n::Lang,
+    settings::dto::{ChatSettings, PunishmentEndNotice},
+    templates::Templates,
+};
+use mongodb::{
+    bson::{doc, Bson, Document},
+    options::{IndexOptions, UpdateOptions},
+    Collection, Database, IndexModel,
+};
+
+#[derive(Debug, Clone)]
+pub struct SettingsRepository {
+    chat_settings: Collection<ChatSettings>,
+}
+
+impl SettingsRepository {
+    pub fn new(db: &Database) -> Self {
+        Self { chat_settings: db.collection("chat_settings") }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder().keys(doc! { "chat_id": 1 }).options(options).build();
+        self.chat_settings.create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Returns settings of the chat, or the default ones if the chat has not
+    /// changed anything.
+    pub async fn get(&self, chat_id: i64) -> Result<ChatSettings, mongodb::error::Error> {
+        let settings = self.chat_settings.find_one(doc! { "chat_id": chat_id }, None).await?;
+        Ok(settings.unwrap_or(ChatSettings { chat_id, ..ChatSettings::default() }))
+    }
+
+    pub async fn set_delete_commands(
+        &self,
+        chat_id: i64,
+        value: bool,
+    ) -> Result<(), mongodb::error::Error> {
+        self.set(chat_id, "delete_commands", value).await
+    }
+
+    pub async fn set_announcement_ttl(
+        &self,
+        chat_id: i64,
+        value: Option<u64>,
+    ) -> Result<(), mongodb::error::Error> {
+        self.set(chat_id, "announcement_ttl", value.map(|x| x as i64)).await
+    }
+
+    pub async fn set_language(
+        &self,
+        chat_id: i64,
+        lang: Lang,
+    ) -> Result<(), mongodb::error::Error> {
+        self.set(chat_id, "language", lang.code()).await
+    }
+
+    pub async fn set_punishment_end_notice(
+        &self,
+        chat_id: i64,
+        notice: PunishmentEndNotice,
+    ) -> Result<(), mongodb::error::Error> {
+        self.set(chat_id, "punishment_end_notice", notice.code()).await
+    }
+
+    /// Overrides the template of the chat, or restores the default one if
+    /// `template` is `None`.
+    pub async fn set_template(
+        &self,
+        chat_id: i64,
+        key: &str,
+        template: Option<&str>,
+    ) -> Result<(), mongodb::error::Error> {
+        let field = format!("templates.{}", key);
+        match template {
+            Some(template) => self.set(chat_id, &field, template).await,
+            None => {
+                self.chat_settings
+                    .update_one(doc! { "chat_id": chat_id }, doc! { "$unset": { field: "" } }, None)
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Replaces all templates overridden by the chat.
+    pub async fn set_templates(
+        &self,
+        chat_id: i64,
+        templates: &Templates,
+    ) -> Result<(), mongodb::error::Error> {
+        let templates = templates
+            .iter()
+            .map(|(key, template)| (key.clone(), Bson::from(template.as_str())))
+            .collect::<Document>();
+        self.set(chat_id, "templates", templates).await
+    }
+
+    async fn set(
+        &self,
+        chat_id: i64,
+        field: &str,
+        value: impl Into<Bson>,
+    ) -> Result<(), mongodb::error::Error> {
+        let mut options = UpdateOptions::default();
+        options.upsert = Some(true);
+
+        self.chat_settings
+            .update_one(
+                doc! { "chat_id": chat_id },
+                doc! { "$set": { field: value.into() } },
+                options,
+            )
+            .await?;
+        Ok(())
+    }
+}
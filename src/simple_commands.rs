@@ -1,29 +1,52 @@
-use crate::{dptree, Message, TBot, BOT_NAME};
+use crate::{
+    dptree,
+    i18n::{self, Lang, Text},
+    settings::SettingsRepository,
+    templates::Placeholders,
+    Message, TBot,
+};
 use teloxide::{prelude2::*, types::ParseMode, utils::command::BotCommand};
 
 #[derive(Debug, Clone, BotCommand)]
 #[command(rename = "lowercase")]
 pub enum SimpleCommands {
-    #[command(description = "shows this chat ID.")]
+    Help,
     ChatId,
-    #[command(description = "shows your ID.")]
     MyId,
 }
 
-pub fn setup_simple_commands() -> crate::Handler {
-    dptree::entry().add_command::<SimpleCommands>(BOT_NAME.into()).branch(dptree::endpoint(
-        |bot: TBot, mes: Message, cmd: SimpleCommands| async move {
+pub fn setup_simple_commands(bot_name: &str) -> crate::Handler {
+    dptree::entry().add_command::<SimpleCommands>(bot_name.into()).branch(dptree::endpoint(
+        |bot: TBot, mes: Message, cmd: SimpleCommands, settings_repo: SettingsRepository| async move {
+            let settings = settings_repo.get(mes.chat.id).await?;
+            // Groups speak English until they choose a language, whoever asks.
+            let user = mes.from().filter(|_| mes.chat.is_private());
+            let lang = Lang::resolve(settings.language, user);
             let text = match cmd {
+                SimpleCommands::Help => {
+                    let commands = i18n::commands(lang)
+                        .iter()
+                        .map(|(command, description)| format!("/{} — {}", command, description))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    i18n::render(lang, Text::Help, &Placeholders::new().text("commands", commands))
+                }
                 SimpleCommands::MyId => {
-                    format!("Your ID: `{}`", mes.from().unwrap().id)
+                    // Channel posts have no sender.
+                    let user = match mes.from() {
+                        Some(user) => user,
+                        None => return Ok(()),
+                    };
+                    let placeholders = Placeholders::new().text("id", user.id);
+                    i18n::render(lang, Text::YourId, &placeholders)
                 }
                 SimpleCommands::ChatId => {
-                    format!("Chat ID: `{}`", mes.chat.id)
+                    i18n::render(lang, Text::ChatId, &Placeholders::new().text("id", mes.chat.id))
                 }
             };
             bot.send_message(mes.chat.id, text)
                 .reply_to_message_id(mes.id)
-                .parse_mode(ParseMode::MarkdownV2)
+                .parse_mode(ParseMode::Html)
                 .await?;
 
             Ok(())
@@ -0,0 +1,135 @@
+//! An HTTP server for monitoring the bot:
+//! - `GET /metrics` returns metrics in the Prometheus text format.
+//! - `GET /healthz` fails if the dispatcher has stopped, so the bot should be
+//!   restarted. It passes while the bot is starting.
+//! - `GET /readyz` fails unless the dispatcher is running and MongoDB is
+//!   reachable, so the bot can handle updates.
+//!
+//! Both health endpoints return a JSON report like
+//! `{"dispatcher":"running","mongo_reachable":true,"last_update":1641038400}`,
+//! where `last_update` is the Unix timestamp of the last update received, or
+//! `null` if there were none since the start.
+
+use crate::metrics;
+use chrono::Utc;
+use mongodb::{bson::doc, Database};
+use serde::Serialize;
+use std::{
+    future::Future,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use teloxide::prelude2::*;
+use warp::{http::StatusCode, Filter};
+
+/// How long to wait for MongoDB to answer a ping.
+const PING_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DispatcherState {
+    Starting,
+    Running,
+    Stopped,
+}
+
+/// What the health endpoints report, updated by the bot as it runs.
+#[derive(Debug)]
+pub struct Health {
+    dispatcher: Mutex<DispatcherState>,
+    /// Unix timestamp in seconds, 0 if no updates were received.
+    last_update: AtomicI64,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self { dispatcher: Mutex::new(DispatcherState::Starting), last_update: AtomicI64::new(0) }
+    }
+}
+
+impl Health {
+    pub fn set_dispatcher(&self, state: DispatcherState) {
+        *self.dispatcher.lock().unwrap() = state;
+    }
+
+    fn record_update(&self) {
+        self.last_update.store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    dispatcher: DispatcherState,
+    mongo_reachable: bool,
+    last_update: Option<i64>,
+}
+
+/// Passes all updates, recording when the last one was received.
+pub fn track_updates(health: Arc<Health>) -> crate::Handler {
+    dptree::filter(move || {
+        health.record_update();
+        async { true }
+    })
+}
+
+/// Binds the status endpoints to the address. The returned future serves them
+/// forever.
+pub fn serve(
+    addr: SocketAddr,
+    health: Arc<Health>,
+    db: Database,
+) -> Result<impl Future<Output = ()>, warp::Error> {
+    let metrics = warp::path("metrics").map(|| {
+        warp::reply::with_header(metrics::render(), "Content-Type", "text/plain; version=0.0.4")
+    });
+    let healthz = {
+        let health = health.clone();
+        let db = db.clone();
+        warp::path("healthz").and_then(move || report(health.clone(), db.clone(), false))
+    };
+    let readyz = warp::path("readyz").and_then(move || report(health.clone(), db.clone(), true));
+    let routes = warp::get().and(metrics.or(healthz).or(readyz).and(warp::path::end()));
+
+    let (addr, server) = warp::serve(routes).try_bind_ephemeral(addr)?;
+    log::info!("Serving status on {}", addr);
+    Ok(server)
+}
+
+async fn report(
+    health: Arc<Health>,
+    db: Database,
+    ready: bool,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // Read before pinging, so the lock is not held across the await.
+    let dispatcher = *health.dispatcher.lock().unwrap();
+    let report = Report {
+        dispatcher,
+        mongo_reachable: mongo_reachable(&db).await,
+        last_update: Some(health.last_update.load(Ordering::Relaxed)).filter(|&t| t > 0),
+    };
+    let ok = if ready {
+        report.dispatcher == DispatcherState::Running && report.mongo_reachable
+    } else {
+        report.dispatcher != DispatcherState::Stopped
+    };
+    let status = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    Ok(warp::reply::with_status(warp::reply::json(&report), status))
+}
+
+async fn mongo_reachable(db: &Database) -> bool {
+    match tokio::time::timeout(PING_TIMEOUT, db.run_command(doc! { "ping": 1 }, None)).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(err)) => {
+            log::warn!("MongoDB ping failed: {}", err);
+            false
+        }
+        Err(_) => {
+            log::warn!("MongoDB did not answer a ping in {:?}", PING_TIMEOUT);
+            false
+        }
+    }
+}
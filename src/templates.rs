@@ -0,0 +1,226 @@
+//! Customizable texts of the bot. Every text has a default template in each
+//! language, which can be overridden by a chat, a warn group or a warn type.
+//!
+//! Templates are HTML and may contain placeholders like `{user}`, which are
+//! substituted with escaped values when rendering.
+
+use crate::i18n::{self, Lang};
+use std::collections::HashMap;
+use teloxide::utils::html;
+
+/// Overridden templates, keyed by [`TemplateKey::name`].
+pub type Templates = HashMap<String, String>;
+
+/// Tags Telegram accepts in HTML messages.
+const HTML_TAGS: &str = "b strong i em u ins s strike del span tg-spoiler a code pre";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKey {
+    /// Placeholders: `{user}`, `{points}`, `{max}`, `{group}`, `{trigger}`,
+    /// `{reason}`.
+    Warned,
+    /// Placeholders: `{user}`, `{points}`, `{max}`, `{group}`, `{trigger}`,
+    /// `{reason}`, `{punishment}`, `{until}`.
+    Punished,
+    /// Placeholders: `{user}`, `{points}`, `{max}`, `{group}`, `{trigger}`,
+    /// `{reason}`, `{error}`.
+    PunishFailed,
+    /// Placeholders: `{user}`, `{punishment}`, `{group}`.
+    PunishmentEnded,
+    SetupAlreadyStarted,
+    SetupAskGroup,
+    SetupCancelled,
+    SetupNoSuchGroup,
+    SetupAskPoints,
+    SetupInvalidPoints,
+    SetupAskTrigger,
+    SetupInvalidTrigger,
+    SetupTriggerExists,
+    SetupAskOnWarn,
+    SetupUseButtons,
+    /// Placeholders: `{trigger}`.
+    SetupDone,
+}
+
+impl TemplateKey {
+    pub const ALL: &'static [TemplateKey] = &[
+        Self::Warned,
+        Self::Punished,
+        Self::PunishFailed,
+        Self::PunishmentEnded,
+        Self::SetupAlreadyStarted,
+        Self::SetupAskGroup,
+        Self::SetupCancelled,
+        Self::SetupNoSuchGroup,
+        Self::SetupAskPoints,
+        Self::SetupInvalidPoints,
+        Self::SetupAskTrigger,
+        Self::SetupInvalidTrigger,
+        Self::SetupTriggerExists,
+        Self::SetupAskOnWarn,
+        Self::SetupUseButtons,
+        Self::SetupDone,
+    ];
+
+    /// The name templates are stored and referenced by.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Warned => "warned",
+            Self::Punished => "punished",
+            Self::PunishFailed => "punish_failed",
+            Self::PunishmentEnded => "punishment_ended",
+            Self::SetupAlreadyStarted => "setup_already_started",
+            Self::SetupAskGroup => "setup_ask_group",
+            Self::SetupCancelled => "setup_cancelled",
+            Self::SetupNoSuchGroup => "setup_no_such_group",
+            Self::SetupAskPoints => "setup_ask_points",
+            Self::SetupInvalidPoints => "setup_invalid_points",
+            Self::SetupAskTrigger => "setup_ask_trigger",
+            Self::SetupInvalidTrigger => "setup_invalid_trigger",
+            Self::SetupTriggerExists => "setup_trigger_exists",
+            Self::SetupAskOnWarn => "setup_ask_on_warn",
+            Self::SetupUseButtons => "setup_use_buttons",
+            Self::SetupDone => "setup_done",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|key| key.name() == name)
+    }
+}
+
+/// Values of placeholders. Text values are HTML-escaped when added.
+#[derive(Debug, Clone, Default)]
+pub struct Placeholders {
+    values: Vec<(&'static str, String)>,
+}
+
+impl Placeholders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.values.push((name, html::escape(&value.to_string())));
+        self
+    }
+
+    /// Adds a value that is already HTML.
+    pub fn html(mut self, name: &'static str, value: String) -> Self {
+        self.values.push((name, value));
+        self
+    }
+
+    /// Adds a mention of a user, i.e. a link that opens the user profile.
+    pub fn mention(self, name: &'static str, user_id: i64, full_name: &str) -> Self {
+        let mention = html::user_mention(user_id, &html::escape(full_name));
+        self.html(name, mention)
+    }
+}
+
+/// Renders the template for `key` from the first of `overrides` that defines
+/// it, falling back to the default one for `lang`. Overrides must go from the
+/// most specific to the least specific.
+pub fn render(
+    key: TemplateKey,
+    lang: Lang,
+    overrides: &[&Templates],
+    placeholders: &Placeholders,
+) -> String {
+    let template = overrides
+        .iter()
+        .find_map(|templates| templates.get(key.name()))
+        .map(|template| template.as_str())
+        .unwrap_or_else(|| i18n::default_template(lang, key));
+    substitute(template, placeholders)
+}
+
+/// Whether Telegram accepts the template as HTML: tags are supported and
+/// balanced, and `<` and `&` are escaped outside of tags. Placeholder values
+/// are escaped, so they cannot make a valid template invalid.
+pub fn is_valid_html(template: &str) -> bool {
+    let mut open = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find(|c| c == '<' || c == '&') {
+        rest = &rest[start..];
+        if rest.starts_with('&') {
+            let entity = rest[1..].split(';').next().unwrap_or("");
+            let valid = match entity.strip_prefix('#') {
+                Some(code) => !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric()),
+                None => !entity.is_empty() && entity.chars().all(|c| c.is_ascii_alphabetic()),
+            };
+            if !valid || !rest[1..].contains(';') {
+                return false;
+            }
+            rest = &rest[entity.len() + 2..];
+            continue;
+        }
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => return false,
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        match tag.strip_prefix('/') {
+            Some(name) => {
+                if open.pop() != Some(name.trim()) {
+                    return false;
+                }
+            }
+            None => {
+                let name = tag.split_whitespace().next().unwrap_or("");
+                if !HTML_TAGS.split(' ').any(|tag| tag == name) {
+                    return false;
+                }
+                open.push(name);
+            }
+        }
+    }
+    open.is_empty()
+}
+
+/// Replaces known placeholders in `template`. Unknown ones are left as is.
+pub fn substitute(template: &str, placeholders: &Placeholders) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            placeholders.values.iter().find(|(n, _)| *n == name).map(|(_, v)| (end, v))
+        });
+        match value {
+            Some((end, value)) => {
+                out.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_telegram_html() {
+        assert!(is_valid_html("{user} has been <b>warned</b> &amp; <a href=\"t.me\">told</a>"));
+        assert!(is_valid_html("<i>nested <code>tags</code></i> &lt;3 &#128512;"));
+    }
+
+    #[test]
+    fn rejects_broken_html() {
+        assert!(!is_valid_html("<b>unclosed"));
+        assert!(!is_valid_html("<b><i>crossed</b></i>"));
+        assert!(!is_valid_html("<div>unsupported</div>"));
+        assert!(!is_valid_html("1 < 2"));
+        assert!(!is_valid_html("you & me"));
+    }
+}
@@ -1,5 +1,21 @@
-use crate::TBot;
-use teloxide::prelude2::*;
+use crate::{metrics, TBot};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use teloxide::{
+    prelude2::*,
+    requests::{Output, Request},
+    types::ResponseParameters,
+    ApiError, RequestError,
+};
+
+/// How many times a request is sent before giving up on flood limits.
+const MAX_SEND_ATTEMPTS: u32 = 3;
 
 pub fn filter_chat_owner() -> crate::Handler {
     dptree::filter(|bot: TBot, mes: Message| async move {
@@ -14,3 +30,146 @@ pub fn filter_chat_owner() -> crate::Handler {
         chat_member.kind.is_owner()
     })
 }
+
+/// Passes messages sent by anonymous chat administrators, i.e. on behalf of the
+/// chat itself. Such messages cannot be attributed to a concrete administrator.
+pub fn filter_anonymous_admin() -> crate::Handler {
+    dptree::filter(|mes: Message| async move {
+        matches!(&mes.sender_chat, Some(chat) if chat.id == mes.chat.id)
+    })
+}
+
+/// Whether the error is a violation of a unique index, e.g. a warn type with
+/// the same trigger was created concurrently.
+pub fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+    use mongodb::error::{ErrorKind, WriteFailure};
+
+    const DUPLICATE_KEY: i32 = 11000;
+    match &*err.kind {
+        ErrorKind::Write(WriteFailure::WriteError(err)) => err.code == DUPLICATE_KEY,
+        ErrorKind::Command(err) => err.code == DUPLICATE_KEY,
+        _ => false,
+    }
+}
+
+/// Sends the request, waiting as long as Telegram asks and sending it again
+/// when the bot hits flood limits, e.g. while warning many users in a raid.
+pub async fn send_with_retry<R>(request: R) -> Result<Output<R>, RequestError>
+where
+    R: Request<Err = RequestError>,
+{
+    retry(|| request.send_ref()).await
+}
+
+/// Like [`send_with_retry`], for requests sent without the Bot API client.
+pub async fn retry<T, F, Fut>(mut send: F) -> Result<T, RequestError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, RequestError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match send().await {
+            Err(RequestError::RetryAfter(secs)) if attempt < MAX_SEND_ATTEMPTS => {
+                log::warn!("Hit a flood limit, retrying in {}s (attempt {})", secs, attempt);
+                metrics::telegram_error("flood_limit");
+                tokio::time::sleep(Duration::from_secs(secs.max(1) as u64)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Bans the channel from sending messages to the chat on its own behalf. The
+/// Bot API client lacks `banChatSenderChat`, so the request is sent by hand.
+pub async fn ban_chat_sender_chat(
+    bot: &TBot,
+    chat_id: i64,
+    sender_chat_id: i64,
+) -> Result<(), RequestError> {
+    #[derive(Serialize)]
+    struct Params {
+        chat_id: i64,
+        sender_chat_id: i64,
+    }
+
+    #[derive(Deserialize)]
+    struct Response {
+        ok: bool,
+        description: Option<ApiError>,
+        parameters: Option<ResponseParameters>,
+    }
+
+    let bot = bot.inner().inner().inner();
+    let url = bot
+        .api_url()
+        .join(&format!("/bot{}/banChatSenderChat", bot.token()))
+        .expect("failed to format url");
+    let response = bot
+        .client()
+        .post(url)
+        .json(&Params { chat_id, sender_chat_id })
+        .send()
+        .await
+        .map_err(RequestError::NetworkError)?;
+    let status_code = response.status();
+    let response: Response = response.json().await.map_err(RequestError::NetworkError)?;
+    match response {
+        Response { ok: true, .. } => Ok(()),
+        Response { parameters: Some(ResponseParameters::RetryAfter(secs)), .. } => {
+            Err(RequestError::RetryAfter(secs))
+        }
+        Response { parameters: Some(ResponseParameters::MigrateToChatId(id)), .. } => {
+            Err(RequestError::MigrateToChatId(id))
+        }
+        Response { description, .. } => Err(RequestError::ApiError {
+            kind: description.unwrap_or_else(|| ApiError::Unknown(String::new())),
+            status_code,
+        }),
+    }
+}
+
+/// Things waiting for someone to press a button. They are forgotten after the
+/// TTL, so buttons nobody presses do not keep them in memory forever.
+pub struct Pending<K, V> {
+    ttl: Duration,
+    items: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K: Eq + Hash, V> Pending<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, items: Mutex::new(HashMap::new()) }
+    }
+
+    /// Adds the item, dropping expired ones.
+    pub fn insert(&self, key: K, value: V) {
+        let now = Instant::now();
+        let mut items = self.items.lock().unwrap();
+        items.retain(|_, (added, _)| now.duration_since(*added) < self.ttl);
+        items.insert(key, (now, value));
+    }
+
+    /// Takes the item out unless it has expired.
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let (added, value) = self.items.lock().unwrap().remove(key)?;
+        Some(value).filter(|_| added.elapsed() < self.ttl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_items_expire() {
+        let pending = Pending::new(Duration::ZERO);
+        pending.insert(1, "expired");
+        assert_eq!(pending.remove(&1), None);
+
+        let pending = Pending::new(Duration::from_secs(60));
+        pending.insert(1, "fresh");
+        assert_eq!(pending.remove(&1), Some("fresh"));
+        assert_eq!(pending.remove(&1), None);
+    }
+}
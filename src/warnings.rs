@@ -1,11 +1,17 @@
 //! This module about structural warnings
 
-mod commands;
+pub(crate) mod commands;
 mod dto;
 mod handlers;
+mod policy;
 mod repository;
 
+pub use dto::{
+    ActivePunishment, AutoTrigger, OnWarnAction, Punishment, PunishmentKind, PunishmentTime,
+    WarningGroup, WarningInfo,
+};
 pub use handlers::{
-    setup_warnings_callback_queries_handler, setup_warnings_handler, SetupWarnState as WarnsState,
+    punishment_ended, setup_warnings_callback_queries_handler, setup_warnings_handler,
+    PendingAnonymousCommands, SetupWarnState as WarnsState,
 };
-pub use repository::WarnsRepository;
+pub use repository::{Upserted, WarnsRepository};
@@ -1,20 +1,41 @@
-use teloxide::utils::command::BotCommand;
+use teloxide::utils::command::{BotCommand, ParseError};
 
 #[derive(Debug, Clone, BotCommand)]
 #[command(rename = "lowercase")]
 pub enum WarnsCommand {
-    #[command(description = "warn a user in `/warn <trigger>` format.")]
-    Warn { trigger: String },
+    #[command(parse_with = "parse_warn_args")]
+    Warn {
+        trigger: String,
+        reason: String,
+    },
+    Immune,
+    Unimmune,
+    Punished,
 }
 
 #[derive(Debug, Clone, BotCommand)]
 #[command(rename = "lowercase")]
 pub enum SetupWarnsCommands {
-    #[command(
-        parse_with = "split",
-        description = "create new warn type for the chat with specified id."
-    )]
-    NewWarn { chat_id: i64 },
-    #[command(description = "cancel creation of the warn.")]
+    #[command(parse_with = "split")]
+    NewWarn {
+        chat_id: i64,
+    },
     Cancel,
 }
+
+#[derive(Debug, Clone, BotCommand)]
+#[command(rename = "lowercase")]
+pub enum EditWarnsCommand {
+    EditWarn { trigger: String },
+    DeleteWarn { trigger: String },
+}
+
+/// Splits `<trigger> [reason]`. The reason is empty if it is not specified.
+fn parse_warn_args(input: String) -> Result<(String, String), ParseError> {
+    let input = input.trim();
+    let (trigger, reason) = match input.split_once(char::is_whitespace) {
+        Some((trigger, reason)) => (trigger, reason.trim()),
+        None => (input, ""),
+    };
+    Ok((trigger.to_string(), reason.to_string()))
+}
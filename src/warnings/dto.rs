@@ -1,18 +1,54 @@
+use crate::{migrations::SCHEMA_VERSION, templates::Templates};
+use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 use teloxide::types::ChatPermissions;
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct UserWarning {
+    /// ID of the warned user, or of the channel if the warned messages were sent
+    /// on behalf of a channel.
     pub user_id: i64,
-    pub info: WarningInfo,
+    pub chat_id: i64,
+    /// `None` for warns of warn types deleted before warns referenced them.
+    pub warn_type_id: Option<ObjectId>,
+    pub trigger: String,
+    /// Points given by the warn type at the time of the warn.
+    pub points: u64,
+    pub group_id: ObjectId,
+    /// Version of the document shape, see [`crate::migrations`].
+    #[serde(default)]
+    pub schema_version: u32,
+}
+
+impl UserWarning {
+    pub fn new(user_id: i64, warn: &WarningInfo) -> Self {
+        Self {
+            user_id,
+            chat_id: warn.chat_id,
+            warn_type_id: Some(warn.id),
+            trigger: warn.trigger.clone(),
+            points: warn.points,
+            group_id: warn.group_id,
+            schema_version: SCHEMA_VERSION,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct WarningInfo {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub chat_id: i64,
     pub trigger: String,
     pub points: u64,
-    pub group: WarningGroup,
+    pub group_id: ObjectId,
     pub on_warn: OnWarnAction,
+    /// Templates overridden for this warn type.
+    #[serde(default, skip_serializing_if = "Templates::is_empty")]
+    pub templates: Templates,
+    /// Version of the document shape, see [`crate::migrations`].
+    #[serde(default)]
+    pub schema_version: u32,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -23,9 +59,18 @@ pub enum OnWarnAction {
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct WarningGroup {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub chat_id: i64,
     pub name: String,
     pub max_points: u64,
     pub punishment: Punishment,
+    /// Templates overridden for warn types of this group.
+    #[serde(default, skip_serializing_if = "Templates::is_empty")]
+    pub templates: Templates,
+    /// Version of the document shape, see [`crate::migrations`].
+    #[serde(default)]
+    pub schema_version: u32,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -47,3 +92,32 @@ pub enum PunishmentKind {
     Mute,
     Restrict(ChatPermissions),
 }
+
+/// A chat member that cannot be warned in the chat.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ImmuneUser {
+    pub chat_id: i64,
+    pub user_id: i64,
+}
+
+/// Warns users whose messages contain `pattern`, ignoring case.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AutoTrigger {
+    pub chat_id: i64,
+    pub pattern: String,
+    /// Trigger of the warn type that is given.
+    pub trigger: String,
+}
+
+/// A temporary punishment that has not ended yet.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ActivePunishment {
+    pub chat_id: i64,
+    pub user_id: i64,
+    pub user_name: String,
+    /// Name of the group the punishment was given for.
+    pub group: String,
+    pub kind: PunishmentKind,
+    /// Unix timestamp in seconds.
+    pub until: i64,
+}
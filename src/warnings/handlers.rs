@@ -1,29 +1,86 @@
+mod auto_warn;
+mod edit_warns;
+mod punishments;
 mod setup_warns;
 mod warn;
 
+pub use punishments::punishment_ended;
+use setup_warns::DialogueError;
 pub use setup_warns::SetupWarnState;
-use std::sync::Arc;
-use teloxide::dispatching2::dialogue::InMemStorage;
+use std::{sync::Arc, time::Duration};
+use teloxide::{
+    dispatching2::dialogue::InMemStorage,
+    types::{InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, Me, ParseMode},
+};
 
 use crate::{
+    cleanup::CleanupQueue,
+    errors,
+    i18n::{self, Text},
+    jobs::Scheduler,
+    metrics,
+    settings::{ChatSettings, SettingsRepository},
+    templates::Placeholders,
     utils,
     warnings::{
-        commands::{SetupWarnsCommands, WarnsCommand},
+        commands::{EditWarnsCommand, SetupWarnsCommands, WarnsCommand},
         repository::WarnsRepository,
     },
-    HandlerOut, TBot, BOT_NAME,
+    HandlerOut, TBot,
 };
+use edit_warns::{EditDialogue, EditWarnState, EditWarnsStorage, EDIT_WARN_PREFIX};
 use teloxide::prelude2::*;
+use warn::WarnTarget;
 
 type WarnsStorage = InMemStorage<SetupWarnState>;
 
-pub fn setup_warnings_handler(storage: Arc<WarnsStorage>) -> crate::Handler {
-    utils::filter_chat_owner()
+const CONFIRM_ANONYMOUS_PREFIX: &str = "confirm_anonymous:";
+
+/// Commands of anonymous administrators the owner did not confirm within this
+/// time are forgotten.
+const ANONYMOUS_COMMAND_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Commands sent by anonymous administrators that wait for the chat owner to
+/// confirm them. Keyed by the chat ID and the command message ID.
+pub struct PendingAnonymousCommands(utils::Pending<(i64, i32), (Message, WarnsCommand)>);
+
+impl Default for PendingAnonymousCommands {
+    fn default() -> Self {
+        Self(utils::Pending::new(ANONYMOUS_COMMAND_TTL))
+    }
+}
+
+pub fn setup_warnings_handler(
+    bot_name: &str,
+    storage: Arc<WarnsStorage>,
+    edit_storage: Arc<EditWarnsStorage>,
+    pending: Arc<PendingAnonymousCommands>,
+) -> crate::Handler {
+    let owner_handler = utils::filter_chat_owner()
         .branch(
             dptree::entry()
-                .add_command::<WarnsCommand>(BOT_NAME.into())
+                .add_command::<WarnsCommand>(bot_name.into())
                 .endpoint(handle_warns_commands),
         )
+        .branch(
+            dptree::filter_map(move || {
+                let edit_storage = edit_storage.clone();
+                async move { Some(edit_storage) }
+            })
+            .add_dialogue::<Message, EditWarnsStorage, EditWarnState>()
+            .branch(
+                dptree::entry()
+                    .add_command::<EditWarnsCommand>(bot_name.into())
+                    .endpoint(edit_warns::handle_edit_warns_commands),
+            )
+            .branch(
+                dptree::filter_map(|dialogue: EditDialogue| async move {
+                    let state = dialogue.current_state().await.ok().flatten();
+                    state.filter(EditWarnState::waits_for_text)
+                })
+                .endpoint(edit_warns::handle_edit_warn_text),
+            ),
+        )
         .branch(
             dptree::filter_map(move || {
                 let storage = storage.clone();
@@ -32,84 +89,297 @@ pub fn setup_warnings_handler(storage: Arc<WarnsStorage>) -> crate::Handler {
             .add_dialogue::<Message, WarnsStorage, SetupWarnState>()
             .branch(
                 dptree::entry()
-                    .add_command::<SetupWarnsCommands>(BOT_NAME.into())
-                    .chain(dptree::filter(|x: Dialogue<SetupWarnState, WarnsStorage>| async move {
-                        match x.current_state().await.unwrap() {
-                            Some(y) => {
-                                if matches!(y, SetupWarnState::WaitForWarnGroup(0)) {
-                                    x.exit().await.unwrap();
-                                }
-                            }
-                            None => {}
-                        }
-                        true
-                    }))
+                    .add_command::<SetupWarnsCommands>(bot_name.into())
                     .endpoint(setup_warns::handle_setup_warns_commands),
             )
             .branch(
-                dptree::entry()
-                    .chain(dptree::filter(|x: Dialogue<SetupWarnState, WarnsStorage>| async move {
-                        match x.current_state().await.unwrap() {
-                            Some(y) => {
-                                if matches!(y, SetupWarnState::WaitForWarnGroup(0)) {
-                                    x.exit().await.unwrap();
-                                    return false;
-                                }
-                            }
-                            None => {}
-                        }
-                        true
-                    }))
-                    .dispatch_by::<SetupWarnState>(),
+                dptree::filter_map(|dialogue: Dialogue<SetupWarnState, WarnsStorage>| async move {
+                    setup_warns::check_started(&dialogue).await
+                })
+                // Filters cannot return errors, so they are returned by an endpoint.
+                .branch(
+                    dptree::filter_map(|res: Result<(), DialogueError>| async move { res.err() })
+                        .endpoint(|err: DialogueError| async move { HandlerOut::Err(err.into()) }),
+                )
+                .branch(dptree::entry().dispatch_by::<SetupWarnState>()),
             ),
+        );
+
+    dptree::entry()
+        .branch(
+            utils::filter_anonymous_admin()
+                .add_command::<WarnsCommand>(bot_name.into())
+                .chain(dptree::filter_map(move || {
+                    let pending = pending.clone();
+                    async move { Some(pending) }
+                }))
+                .endpoint(ask_anonymous_admin_confirmation),
+        )
+        .branch(owner_handler)
+        .branch(
+            auto_warn::filter_plain_text()
+                // Nobody asked for auto warns, so only operators hear of their errors.
+                .chain(errors::quiet_message_errors())
+                .endpoint(auto_warn::handle_auto_triggers),
         )
 }
 
-pub fn setup_warnings_callback_queries_handler(storage: Arc<WarnsStorage>) -> crate::Handler {
-    dptree::entry().branch(
-        dptree::filter_map(move || {
-            let storage = storage.clone();
-            async move { Some(storage) }
-        })
-        .add_dialogue::<CallbackQuery, WarnsStorage, SetupWarnState>()
-        .endpoint(setup_warns::wait_for_on_warn_callback_query_handler),
-    )
+pub fn setup_warnings_callback_queries_handler(
+    storage: Arc<WarnsStorage>,
+    edit_storage: Arc<EditWarnsStorage>,
+    pending: Arc<PendingAnonymousCommands>,
+) -> crate::Handler {
+    dptree::entry()
+        .branch(
+            dptree::filter(|q: CallbackQuery| async move {
+                matches!(&q.data, Some(data) if data.starts_with(EDIT_WARN_PREFIX))
+            })
+            .chain(dptree::filter_map(move || {
+                let edit_storage = edit_storage.clone();
+                async move { Some(edit_storage) }
+            }))
+            .add_dialogue::<CallbackQuery, EditWarnsStorage, EditWarnState>()
+            .endpoint(edit_warns::handle_edit_warn_callback),
+        )
+        .branch(
+            dptree::filter(|q: CallbackQuery| async move {
+                matches!(&q.data, Some(data) if data.starts_with(CONFIRM_ANONYMOUS_PREFIX))
+            })
+            .chain(dptree::filter_map(move || {
+                let pending = pending.clone();
+                async move { Some(pending) }
+            }))
+            .endpoint(confirm_anonymous_command),
+        )
+        .branch(
+            dptree::filter_map(move || {
+                let storage = storage.clone();
+                async move { Some(storage) }
+            })
+            .add_dialogue::<CallbackQuery, WarnsStorage, SetupWarnState>()
+            .endpoint(setup_warns::wait_for_on_warn_callback_query_handler),
+        )
 }
 
+async fn ask_anonymous_admin_confirmation(
+    bot: TBot,
+    mes: Message,
+    cmd: WarnsCommand,
+    settings_repo: SettingsRepository,
+    pending: Arc<PendingAnonymousCommands>,
+) -> HandlerOut {
+    let lang = settings_repo.get(mes.chat.id).await?.lang();
+    pending.0.insert((mes.chat.id, mes.id), (mes.clone(), cmd));
+
+    let kb = InlineKeyboardMarkup::new([[InlineKeyboardButton::new(
+        i18n::text(lang, Text::ConfirmButton),
+        InlineKeyboardButtonKind::CallbackData(format!("{}{}", CONFIRM_ANONYMOUS_PREFIX, mes.id)),
+    )]]);
+    bot.send_message(mes.chat.id, i18n::text(lang, Text::AnonymousConfirmation))
+        .reply_to_message_id(mes.id)
+        .reply_markup(kb)
+        .await?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn confirm_anonymous_command(
+    bot: TBot,
+    q: CallbackQuery,
+    repo: WarnsRepository,
+    settings_repo: SettingsRepository,
+    cleanup: CleanupQueue,
+    scheduler: Scheduler,
+    me: Me,
+    pending: Arc<PendingAnonymousCommands>,
+) -> HandlerOut {
+    let mes = match &q.message {
+        Some(mes) => mes,
+        None => return Ok(()),
+    };
+    let command_id = match q
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(CONFIRM_ANONYMOUS_PREFIX))
+        .and_then(|id| id.parse::<i32>().ok())
+    {
+        Some(id) => id,
+        None => {
+            log::warn!("Unexpected data: {:?}", &q.data);
+            return Ok(());
+        }
+    };
+
+    let lang = settings_repo.get(mes.chat.id).await?.lang();
+    let member = bot.get_chat_member(mes.chat.id, q.from.id).await?;
+    if !member.kind.is_owner() {
+        bot.answer_callback_query(q.id)
+            .text(i18n::text(lang, Text::OnlyOwnerConfirms))
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+    bot.answer_callback_query(q.id).await?;
+
+    let command = pending.0.remove(&(mes.chat.id, command_id));
+    match command {
+        Some((command_mes, cmd)) => {
+            let placeholders = Placeholders::new().mention("user", q.from.id, &q.from.full_name());
+            let text = i18n::render(lang, Text::ConfirmedBy, &placeholders);
+            bot.edit_message_text(mes.chat.id, mes.id, text).parse_mode(ParseMode::Html).await?;
+            handle_warns_commands(
+                bot,
+                command_mes,
+                cmd,
+                repo,
+                settings_repo,
+                cleanup,
+                scheduler,
+                me,
+            )
+            .await?;
+        }
+        None => {
+            let text = i18n::text(lang, Text::AlreadyHandled);
+            bot.edit_message_text(mes.chat.id, mes.id, text).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn handle_warns_commands(
     bot: TBot,
     mes: Message,
     cmd: WarnsCommand,
     repo: WarnsRepository,
+    settings_repo: SettingsRepository,
+    cleanup: CleanupQueue,
+    scheduler: Scheduler,
+    me: Me,
 ) -> HandlerOut {
+    let _timer = metrics::handler_timer("warns_commands");
+    let settings = settings_repo.get(mes.chat.id).await?;
+    let lang = settings.lang();
+    let send = |text: Text, placeholders: Placeholders| {
+        bot.send_message(mes.chat.id, i18n::render(lang, text, &placeholders))
+            .parse_mode(ParseMode::Html)
+    };
     match cmd {
-        WarnsCommand::Warn { trigger } => {
-            let reply_to_message = match mes.reply_to_message() {
-                Some(mes) => mes.clone(),
+        WarnsCommand::Warn { trigger, reason } => {
+            let result = warn_by_command(
+                &bot, &me, &mes, &trigger, &reason, repo, &settings, &cleanup, &scheduler,
+            )
+            .await;
+            // Failed commands are deleted too, the error is reported without
+            // replying to them.
+            if settings.delete_commands {
+                if let Err(err) = bot.delete_message(mes.chat.id, mes.id).await {
+                    log::warn!("Failed to delete the command {}: {}", mes.id, err);
+                    metrics::telegram_error("delete_command");
+                }
+            }
+            result?;
+        }
+        WarnsCommand::Immune => {
+            let target = match mes.reply_to_message().and_then(WarnTarget::from_message) {
+                Some(target) => target,
                 None => {
-                    bot.send_message(mes.chat.id, "Reply to a user message to warn.").await?;
+                    send(Text::ReplyToImmune, Placeholders::new()).await?;
                     return Ok(());
                 }
             };
-            let reply_to = match reply_to_message.from() {
-                Some(user) => user.clone(),
+            repo.add_immune_user(mes.chat.id, target.id()).await?;
+            send(Text::Immune, target.placeholder("user", Placeholders::new())).await?;
+        }
+        WarnsCommand::Unimmune => {
+            let target = match mes.reply_to_message().and_then(WarnTarget::from_message) {
+                Some(target) => target,
                 None => {
-                    bot.send_message(mes.chat.id, "Reply to a user message to warn.").await?;
+                    send(Text::ReplyToUnimmune, Placeholders::new()).await?;
                     return Ok(());
                 }
             };
-            let warn_info = repo.find_warn_by_trigger(trigger.as_str()).await?;
-            match warn_info {
-                Some(warn) => {
-                    warn::warn_user(bot.clone(), mes, repo, reply_to, &warn).await?;
-                    warn::on_warn(bot, &reply_to_message, warn.on_warn).await?;
-                }
-                None => {
-                    bot.send_message(mes.chat.id, "There are no such warning type.").await?;
-                }
-            }
+            let text = if repo.remove_immune_user(mes.chat.id, target.id()).await? {
+                Text::Unimmune
+            } else {
+                Text::NotImmune
+            };
+            send(text, target.placeholder("user", Placeholders::new())).await?;
+        }
+        WarnsCommand::Punished => {
+            let text = punishments::active_punishments_text(&repo, mes.chat.id, lang).await?;
+            bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn warn_by_command(
+    bot: &TBot,
+    me: &Me,
+    mes: &Message,
+    trigger: &str,
+    reason: &str,
+    repo: WarnsRepository,
+    settings: &ChatSettings,
+    cleanup: &CleanupQueue,
+    scheduler: &Scheduler,
+) -> HandlerOut {
+    let lang = settings.lang();
+    let announce = |text: Text, placeholders: Placeholders| {
+        cleanup.announce(bot, settings, i18n::render(lang, text, &placeholders))
+    };
+    let reply_to_message = match mes.reply_to_message() {
+        Some(mes) => mes.clone(),
+        None => {
+            announce(Text::ReplyToWarn, Placeholders::new()).await?;
+            return Ok(());
+        }
+    };
+    let target = match WarnTarget::from_message(&reply_to_message) {
+        Some(target) => target,
+        None => {
+            announce(Text::ReplyToWarn, Placeholders::new()).await?;
+            return Ok(());
         }
+    };
+    if let Some(protection) = warn::protection_reason(bot, me, &repo, mes.chat.id, &target).await? {
+        announce(protection, Placeholders::new()).await?;
+        return Ok(());
     }
+    let (warn, group) = match repo.find_warn_and_group(mes.chat.id, trigger).await? {
+        Some(found) => found,
+        None => {
+            announce(Text::NoSuchWarnType, Placeholders::new()).await?;
+            return Ok(());
+        }
+    };
+    let missing = warn::missing_bot_rights(bot, me, mes.chat.id, &warn).await?;
+    if !missing.is_empty() {
+        let rights =
+            missing.into_iter().map(|right| i18n::text(lang, right)).collect::<Vec<_>>().join(", ");
+        announce(Text::MissingRights, Placeholders::new().text("rights", rights)).await?;
+        return Ok(());
+    }
+    warn::warn_user(
+        bot.clone(),
+        mes.clone(),
+        repo,
+        cleanup,
+        scheduler,
+        settings,
+        target,
+        &warn,
+        &group,
+        reason,
+    )
+    .await?;
+    warn::on_warn(bot.clone(), lang, &reply_to_message, warn.on_warn).await?;
 
     Ok(())
 }
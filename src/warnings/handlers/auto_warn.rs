@@ -0,0 +1,83 @@
+use crate::{
+    cleanup::CleanupQueue,
+    i18n::{self, Text},
+    jobs::Scheduler,
+    metrics,
+    settings::SettingsRepository,
+    warnings::{
+        handlers::warn::{self, WarnTarget},
+        repository::WarnsRepository,
+    },
+    HandlerOut, TBot,
+};
+use teloxide::{prelude2::*, types::Me};
+
+/// Passes text messages which are not commands.
+pub(super) fn filter_plain_text() -> crate::Handler {
+    dptree::filter(|mes: Message| async move {
+        matches!(mes.text(), Some(text) if !text.starts_with('/'))
+    })
+}
+
+/// Warns the sender if the message matches an auto trigger of the chat.
+pub(super) async fn handle_auto_triggers(
+    bot: TBot,
+    mes: Message,
+    repo: WarnsRepository,
+    settings_repo: SettingsRepository,
+    cleanup: CleanupQueue,
+    scheduler: Scheduler,
+    me: Me,
+) -> HandlerOut {
+    let _timer = metrics::handler_timer("auto_triggers");
+    let text = match mes.text() {
+        Some(text) => text.to_lowercase(),
+        None => return Ok(()),
+    };
+    let rules = repo.get_auto_triggers(mes.chat.id).await?;
+    let rule = match rules.iter().find(|rule| text.contains(&rule.pattern.to_lowercase())) {
+        Some(rule) => rule,
+        None => return Ok(()),
+    };
+    metrics::AUTO_TRIGGER_HITS.with_label_values(&[&rule.trigger]).inc();
+    let target = match WarnTarget::from_message(&mes) {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    if warn::protection_reason(&bot, &me, &repo, mes.chat.id, &target).await?.is_some() {
+        return Ok(());
+    }
+    let (warn, group) = match repo.find_warn_and_group(mes.chat.id, &rule.trigger).await? {
+        Some(found) => found,
+        None => {
+            log::warn!("Auto trigger `{}` refers to a missing warn type", rule.pattern);
+            return Ok(());
+        }
+    };
+    // Nobody asked for this warn, so there is no one to complain to about rights.
+    let missing = warn::missing_bot_rights(&bot, &me, mes.chat.id, &warn).await?;
+    if !missing.is_empty() {
+        log::warn!("Cannot auto warn in chat {}: missing rights {:?}", mes.chat.id, missing);
+        return Ok(());
+    }
+
+    let settings = settings_repo.get(mes.chat.id).await?;
+    let lang = settings.lang();
+    let reason = i18n::text(lang, Text::AutoWarnReason);
+    warn::warn_user(
+        bot.clone(),
+        mes.clone(),
+        repo,
+        &cleanup,
+        &scheduler,
+        &settings,
+        target,
+        &warn,
+        &group,
+        reason,
+    )
+    .await?;
+    warn::on_warn(bot, lang, &mes, warn.on_warn).await?;
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use crate::{
+    i18n::{self, Lang, Text},
+    metrics,
+    settings::SettingsRepository,
+    templates::Placeholders,
+    warnings::{
+        commands::EditWarnsCommand,
+        dto::{OnWarnAction, WarningGroup, WarningInfo},
+    },
+    HandlerOut, TBot, WarnsRepository,
+};
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    dispatching2::dialogue::InMemStorage,
+    prelude2::*,
+    types::{InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, ParseMode},
+};
+
+pub(super) const EDIT_WARN_PREFIX: &str = "edit_warn:";
+
+pub type EditWarnsStorage = InMemStorage<EditWarnState>;
+pub(super) type EditDialogue = Dialogue<EditWarnState, EditWarnsStorage>;
+
+/// Editing of a warn type in the chat where the dialogue goes.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum EditWarnState {
+    ChooseField { trigger: String },
+    WaitForPoints { trigger: String },
+    WaitForGroup { trigger: String },
+    WaitForOnWarn { trigger: String },
+    ConfirmDelete { trigger: String },
+}
+
+impl EditWarnState {
+    fn trigger(&self) -> &str {
+        match self {
+            Self::ChooseField { trigger }
+            | Self::WaitForPoints { trigger }
+            | Self::WaitForGroup { trigger }
+            | Self::WaitForOnWarn { trigger }
+            | Self::ConfirmDelete { trigger } => trigger,
+        }
+    }
+
+    /// Whether the dialogue waits for a text message rather than a button.
+    pub(super) fn waits_for_text(&self) -> bool {
+        matches!(self, Self::WaitForPoints { .. } | Self::WaitForGroup { .. })
+    }
+}
+
+fn button(lang: Lang, text: Text, data: &str) -> InlineKeyboardButton {
+    InlineKeyboardButton::new(
+        i18n::text(lang, text),
+        InlineKeyboardButtonKind::CallbackData(format!("{}{}", EDIT_WARN_PREFIX, data)),
+    )
+}
+
+fn dialogue_event(event: &str) {
+    metrics::DIALOGUES.with_label_values(&["edit_warn", event]).inc();
+}
+
+fn on_warn_text(on_warn: &OnWarnAction) -> Text {
+    match on_warn {
+        OnWarnAction::DeleteMessage => Text::DeleteMessageButton,
+        OnWarnAction::Nothing => Text::DoNothingButton,
+    }
+}
+
+pub(super) async fn handle_edit_warns_commands(
+    bot: TBot,
+    mes: Message,
+    dialogue: EditDialogue,
+    cmd: EditWarnsCommand,
+    repo: WarnsRepository,
+    settings_repo: SettingsRepository,
+) -> HandlerOut {
+    let _timer = metrics::handler_timer("edit_warns_commands");
+    let chat_id = mes.chat.id;
+    let lang = settings_repo.get(chat_id).await?.lang();
+    let trigger = match &cmd {
+        EditWarnsCommand::EditWarn { trigger } | EditWarnsCommand::DeleteWarn { trigger } => {
+            trigger.trim().to_string()
+        }
+    };
+    let (warn, group) = match repo.find_warn_and_group(chat_id, &trigger).await? {
+        Some(found) => found,
+        None => {
+            bot.send_message(chat_id, i18n::text(lang, Text::NoSuchWarnType)).await?;
+            return Ok(());
+        }
+    };
+
+    match cmd {
+        EditWarnsCommand::EditWarn { .. } => {
+            let kb = InlineKeyboardMarkup::new([
+                vec![
+                    button(lang, Text::PointsButton, "points"),
+                    button(lang, Text::GroupButton, "group"),
+                    button(lang, Text::OnWarnButton, "on_warn"),
+                ],
+                vec![button(lang, Text::CancelButton, "cancel")],
+            ]);
+            let text =
+                i18n::render(lang, Text::EditWarnChoose, &warn_placeholders(lang, &warn, &group));
+            bot.send_message(chat_id, text).parse_mode(ParseMode::Html).reply_markup(kb).await?;
+            dialogue.next(EditWarnState::ChooseField { trigger }).await?;
+            dialogue_event("started");
+        }
+        EditWarnsCommand::DeleteWarn { .. } => {
+            let warns = repo.count_actual_warns_by_trigger(chat_id, &trigger).await?;
+            let kb = InlineKeyboardMarkup::new([[
+                button(lang, Text::DeleteButton, "confirm_delete"),
+                button(lang, Text::CancelButton, "cancel"),
+            ]]);
+            let placeholders = Placeholders::new().text("trigger", &trigger).text("warns", warns);
+            let text = i18n::render(lang, Text::DeleteWarnConfirm, &placeholders);
+            bot.send_message(chat_id, text).parse_mode(ParseMode::Html).reply_markup(kb).await?;
+            dialogue.next(EditWarnState::ConfirmDelete { trigger }).await?;
+            dialogue_event("started");
+        }
+    }
+
+    Ok(())
+}
+
+fn warn_placeholders(lang: Lang, warn: &WarningInfo, group: &WarningGroup) -> Placeholders {
+    Placeholders::new()
+        .text("trigger", &warn.trigger)
+        .text("points", warn.points)
+        .text("group", &group.name)
+        .text("on_warn", i18n::text(lang, on_warn_text(&warn.on_warn)))
+}
+
+pub(super) async fn handle_edit_warn_text(
+    bot: TBot,
+    mes: Message,
+    dialogue: EditDialogue,
+    state: EditWarnState,
+    repo: WarnsRepository,
+    settings_repo: SettingsRepository,
+) -> HandlerOut {
+    let chat_id = mes.chat.id;
+    let lang = settings_repo.get(chat_id).await?.lang();
+    let send = |text: Text, placeholders: Placeholders| {
+        bot.send_message(chat_id, i18n::render(lang, text, &placeholders))
+            .parse_mode(ParseMode::Html)
+    };
+    let text = match mes.text() {
+        Some(text) => text.trim(),
+        None => return Ok(()),
+    };
+
+    match &state {
+        EditWarnState::WaitForPoints { trigger } => {
+            let points = match text.parse::<u64>() {
+                Ok(points) if points > 0 => points,
+                _ => {
+                    send(Text::EditInvalidPoints, Placeholders::new()).await?;
+                    return Ok(());
+                }
+            };
+            repo.set_warn_type_points(chat_id, trigger, points).await?;
+        }
+        EditWarnState::WaitForGroup { trigger } => {
+            let group = match repo.get_warn_group(chat_id, text).await? {
+                Some(group) => group,
+                None => {
+                    send(Text::EditNoSuchGroup, Placeholders::new()).await?;
+                    return Ok(());
+                }
+            };
+            repo.set_warn_type_group(chat_id, trigger, &group).await?;
+        }
+        _ => return Ok(()),
+    }
+    dialogue.exit().await?;
+    dialogue_event("completed");
+    send(Text::WarnTypeUpdated, Placeholders::new().text("trigger", state.trigger())).await?;
+
+    Ok(())
+}
+
+pub(super) async fn handle_edit_warn_callback(
+    bot: TBot,
+    q: CallbackQuery,
+    dialogue: EditDialogue,
+    repo: WarnsRepository,
+    settings_repo: SettingsRepository,
+) -> HandlerOut {
+    let mes = match &q.message {
+        Some(mes) => mes,
+        None => return Ok(()),
+    };
+    let chat_id = mes.chat.id;
+    let lang = settings_repo.get(chat_id).await?.lang();
+    let member = bot.get_chat_member(chat_id, q.from.id).await?;
+    if !member.kind.is_owner() {
+        bot.answer_callback_query(q.id)
+            .text(i18n::text(lang, Text::OnlyOwnerConfirms))
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+    bot.answer_callback_query(q.id).await?;
+
+    let state = match dialogue.current_state().await? {
+        Some(state) => state,
+        None => {
+            bot.edit_message_text(chat_id, mes.id, i18n::text(lang, Text::AlreadyHandled)).await?;
+            return Ok(());
+        }
+    };
+    let trigger = state.trigger().to_string();
+    let action = q.data.as_deref().and_then(|data| data.strip_prefix(EDIT_WARN_PREFIX));
+    let edit = |text: Text| {
+        let placeholders = Placeholders::new().text("trigger", &trigger);
+        bot.edit_message_text(chat_id, mes.id, i18n::render(lang, text, &placeholders))
+            .parse_mode(ParseMode::Html)
+    };
+
+    match (&state, action) {
+        (_, Some("cancel")) => {
+            dialogue.exit().await?;
+            dialogue_event("cancelled");
+            edit(Text::EditCancelled).await?;
+        }
+        (EditWarnState::ChooseField { .. }, Some("points")) => {
+            dialogue.next(EditWarnState::WaitForPoints { trigger: trigger.clone() }).await?;
+            edit(Text::EditAskPoints).await?;
+        }
+        (EditWarnState::ChooseField { .. }, Some("group")) => {
+            dialogue.next(EditWarnState::WaitForGroup { trigger: trigger.clone() }).await?;
+            edit(Text::EditAskGroup).await?;
+        }
+        (EditWarnState::ChooseField { .. }, Some("on_warn")) => {
+            dialogue.next(EditWarnState::WaitForOnWarn { trigger: trigger.clone() }).await?;
+            let kb = InlineKeyboardMarkup::new([
+                [button(lang, Text::DeleteMessageButton, "delete_message")],
+                [button(lang, Text::DoNothingButton, "nothing")],
+            ]);
+            edit(Text::EditAskOnWarn).reply_markup(kb).await?;
+        }
+        (EditWarnState::WaitForOnWarn { .. }, Some(value @ ("delete_message" | "nothing"))) => {
+            let on_warn = match value {
+                "delete_message" => OnWarnAction::DeleteMessage,
+                _ => OnWarnAction::Nothing,
+            };
+            repo.set_warn_type_on_warn(chat_id, &trigger, on_warn).await?;
+            dialogue.exit().await?;
+            dialogue_event("completed");
+            edit(Text::WarnTypeUpdated).await?;
+        }
+        (EditWarnState::ConfirmDelete { .. }, Some("confirm_delete")) => {
+            dialogue.exit().await?;
+            dialogue_event("completed");
+            let text = if repo.delete_warn_type(chat_id, &trigger).await? {
+                Text::WarnTypeDeleted
+            } else {
+                Text::NoSuchWarnType
+            };
+            edit(text).await?;
+        }
+        // Buttons of an older message of the dialogue.
+        _ => {
+            log::warn!("Unexpected data: {:?}", &q.data);
+        }
+    }
+
+    Ok(())
+}
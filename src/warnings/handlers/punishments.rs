@@ -0,0 +1,102 @@
+use crate::{
+    i18n::{self, Lang, Text},
+    jobs::JobContext,
+    metrics,
+    settings::PunishmentEndNotice,
+    templates::{self, Placeholders, TemplateKey},
+    warnings::{handlers::warn, repository::WarnsRepository},
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use teloxide::{prelude2::*, types::ParseMode};
+
+/// Announces the end of the punishment of the user, if it has ended.
+pub async fn punishment_ended(ctx: &JobContext, chat_id: i64, user_id: i64) -> anyhow::Result<()> {
+    let punishment = match ctx.repo.get_active_punishment(chat_id, user_id).await? {
+        Some(punishment) => punishment,
+        None => return Ok(()),
+    };
+    // The user may have been punished again in the meantime.
+    if punishment.until > Utc::now().timestamp() {
+        return Ok(());
+    }
+
+    let settings = ctx.settings_repo.get(chat_id).await?;
+    let lang = settings.lang();
+    let group_templates = ctx
+        .repo
+        .get_warn_group(chat_id, &punishment.group)
+        .await?
+        .map(|group| group.templates)
+        .unwrap_or_default();
+    let overrides = [&group_templates, &settings.templates];
+    let placeholders = Placeholders::new()
+        .mention("user", user_id, &punishment.user_name)
+        .text("punishment", i18n::text(lang, warn::punishment_text(&punishment.kind)))
+        .text("group", &punishment.group);
+    let text = templates::render(TemplateKey::PunishmentEnded, lang, &overrides, &placeholders);
+
+    match settings.punishment_end_notice {
+        PunishmentEndNotice::Chat => {
+            ctx.cleanup.announce(&ctx.bot, &settings, text).await?;
+        }
+        PunishmentEndNotice::Private => {
+            // Users who never started the bot cannot be messaged, so there is no reason to
+            // retry.
+            if let Err(err) = ctx.bot.send_message(user_id, text).parse_mode(ParseMode::Html).await
+            {
+                log::warn!("Failed to notify {} about the end of punishment: {}", user_id, err);
+                metrics::telegram_error("punishment_end_notice");
+            }
+        }
+        PunishmentEndNotice::Off => {}
+    }
+    ctx.repo.remove_active_punishment(chat_id, user_id).await?;
+
+    Ok(())
+}
+
+/// Renders the list of punishments in the chat that have not ended yet.
+pub(super) async fn active_punishments_text(
+    repo: &WarnsRepository,
+    chat_id: i64,
+    lang: Lang,
+) -> anyhow::Result<String> {
+    let now = Utc::now().timestamp();
+    let punishments = repo.get_active_punishments(chat_id, now).await?;
+    if punishments.is_empty() {
+        return Ok(i18n::text(lang, Text::NoActivePunishments).to_string());
+    }
+
+    let list = punishments
+        .iter()
+        .map(|punishment| {
+            let until =
+                DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(punishment.until, 0), Utc);
+            let placeholders = Placeholders::new()
+                .mention("user", punishment.user_id, &punishment.user_name)
+                .text("punishment", i18n::text(lang, warn::punishment_text(&punishment.kind)))
+                .text("group", &punishment.group)
+                .html("until", warn::until_text(lang, until))
+                .text("left", duration_text(lang, punishment.until - now));
+            i18n::render(lang, Text::ActivePunishment, &placeholders)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(i18n::render(lang, Text::ActivePunishments, &Placeholders::new().html("punishments", list)))
+}
+
+/// Formats the duration like `1d 2h 5m`, rounding up to minutes.
+fn duration_text(lang: Lang, secs: i64) -> String {
+    let minutes = (secs + 59) / 60;
+    let parts = [
+        (minutes / (24 * 60), Text::Days),
+        (minutes % (24 * 60) / 60, Text::Hours),
+        (minutes % 60, Text::Minutes),
+    ];
+    parts
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, text)| i18n::render(lang, *text, &Placeholders::new().text("n", n)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -1,4 +1,10 @@
 use crate::{
+    i18n::{self, Text},
+    metrics,
+    migrations::SCHEMA_VERSION,
+    settings::SettingsRepository,
+    templates::{self, Placeholders, TemplateKey, Templates},
+    utils,
     warnings::{
         commands::SetupWarnsCommands,
         dto::{OnWarnAction, WarningGroup, WarningInfo},
@@ -6,11 +12,13 @@ use crate::{
     },
     HandlerOut, TBot, WarnsRepository,
 };
+use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use teloxide::{
     macros::DialogueState,
     prelude2::*,
-    types::{InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup},
+    types::{InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, ParseMode},
 };
 
 pub async fn handle_setup_warns_commands(
@@ -18,32 +26,54 @@ pub async fn handle_setup_warns_commands(
     mes: Message,
     dialogue: Dialogue,
     cmd: SetupWarnsCommands,
+    settings_repo: SettingsRepository,
 ) -> HandlerOut {
+    let _timer = metrics::handler_timer("setup_warns_commands");
     match cmd {
         SetupWarnsCommands::NewWarn { chat_id } => {
-            match dialogue.current_state().await? {
-                Some(_) => {
-                    bot.send_message(mes.chat.id, "You already setup new warn type.").await?;
-                    return Ok(());
+            let key = match dialogue.current_state().await? {
+                Some(state) if state.is_started() => TemplateKey::SetupAlreadyStarted,
+                _ => {
+                    dialogue.next(SetupWarnState::WaitForWarnGroup(chat_id)).await?;
+                    metrics::DIALOGUES.with_label_values(&["setup_warn", "started"]).inc();
+                    TemplateKey::SetupAskGroup
                 }
-                None => {}
             };
-            dialogue.next(SetupWarnState::WaitForWarnGroup(chat_id)).await?;
-            bot.send_message(
-                mes.chat.id,
-                "Good. Send me the name of the warn group the warn must relate to.",
-            )
-            .await?;
+            let text = render(&settings_repo, chat_id, key, &Placeholders::new()).await?;
+            utils::send_with_retry(bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html))
+                .await?;
         }
         SetupWarnsCommands::Cancel => {
+            let chat_id = match dialogue.current_state().await? {
+                Some(state) if state.is_started() => {
+                    metrics::DIALOGUES.with_label_values(&["setup_warn", "cancelled"]).inc();
+                    state.chat_id()
+                }
+                _ => mes.chat.id,
+            };
             dialogue.exit().await?;
-            bot.send_message(mes.chat.id, "Cancelled.").await?;
+            let text =
+                render(&settings_repo, chat_id, TemplateKey::SetupCancelled, &Placeholders::new())
+                    .await?;
+            utils::send_with_retry(bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html))
+                .await?;
         }
     }
 
     Ok(())
 }
 
+/// Renders the text for `key` using templates of the chat being set up.
+async fn render(
+    settings_repo: &SettingsRepository,
+    chat_id: i64,
+    key: TemplateKey,
+    placeholders: &Placeholders,
+) -> anyhow::Result<String> {
+    let settings = settings_repo.get(chat_id).await?;
+    Ok(templates::render(key, settings.lang(), &[&settings.templates], placeholders))
+}
+
 #[derive(DialogueState, Clone, Serialize, Deserialize)]
 #[handler_out(anyhow::Result<()>)]
 pub enum SetupWarnState {
@@ -66,6 +96,23 @@ impl Default for SetupWarnState {
     }
 }
 
+impl SetupWarnState {
+    /// The default state is stored for users who have not started a setup.
+    pub fn is_started(&self) -> bool {
+        !matches!(self, Self::WaitForWarnGroup(0))
+    }
+
+    /// ID of the chat the warn type is created for.
+    pub fn chat_id(&self) -> i64 {
+        match self {
+            Self::WaitForWarnGroup(chat_id) => *chat_id,
+            Self::WaitForPoints(state) => state.chat_id,
+            Self::WaitForTrigger(state) => state.chat_id,
+            Self::WaitForOnWarn(state) => state.chat_id,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct WaitForPointsState {
     chat_id: i64,
@@ -89,36 +136,63 @@ pub struct WaitForOnWarnState {
 
 type Dialogue = teloxide::dispatching2::dialogue::Dialogue<SetupWarnState, WarnsStorage>;
 
+/// A failure to access the dialogue storage in a filter.
+#[derive(Debug, Clone)]
+pub(super) struct DialogueError(String);
+
+impl fmt::Display for DialogueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot access the setup dialogue: {}", self.0)
+    }
+}
+
+impl std::error::Error for DialogueError {}
+
+/// Returns `None` if the user has not started a setup, removing the default
+/// state stored for them.
+pub(super) async fn check_started(dialogue: &Dialogue) -> Option<Result<(), DialogueError>> {
+    match dialogue.current_state().await {
+        Ok(Some(state)) if !state.is_started() => match dialogue.exit().await {
+            Ok(()) => None,
+            Err(err) => Some(Err(DialogueError(err.to_string()))),
+        },
+        Ok(_) => Some(Ok(())),
+        Err(err) => Some(Err(DialogueError(err.to_string()))),
+    }
+}
+
 async fn wait_for_warn_group_handler(
     bot: TBot,
     mes: Message,
     dialogue: Dialogue,
     repo: WarnsRepository,
+    settings_repo: SettingsRepository,
     chat_id: i64,
 ) -> HandlerOut {
     let text = match mes.text() {
         Some(text) => text,
         None => return Ok(()),
     };
-    let group = repo.get_warn_group(text).await?;
+    let group = repo.get_warn_group(chat_id, text).await?;
     let group = match group {
         Some(g) => g,
         None => {
-            bot.send_message(
-                mes.chat.id,
-                "There are no such warn group. Send me the name of the warn group the warn must \
-                 relate to.",
+            let text = render(
+                &settings_repo,
+                chat_id,
+                TemplateKey::SetupNoSuchGroup,
+                &Placeholders::new(),
             )
             .await?;
+            utils::send_with_retry(bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html))
+                .await?;
             return Ok(());
         }
     };
     dialogue.next(SetupWarnState::WaitForPoints(WaitForPointsState { chat_id, group })).await?;
-    bot.send_message(
-        mes.chat.id,
-        "Good. Now send me amount of the points the user will receive by this warn.",
-    )
-    .await?;
+    let text =
+        render(&settings_repo, chat_id, TemplateKey::SetupAskPoints, &Placeholders::new()).await?;
+    utils::send_with_retry(bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html)).await?;
 
     Ok(())
 }
@@ -127,6 +201,7 @@ async fn wait_for_points_handler(
     bot: TBot,
     mes: Message,
     dialogue: Dialogue,
+    settings_repo: SettingsRepository,
     state: WaitForPointsState,
 ) -> HandlerOut {
     let text = match mes.text() {
@@ -136,11 +211,15 @@ async fn wait_for_points_handler(
     let max_points = match text.parse::<u64>() {
         Ok(p) => p,
         Err(_) => {
-            bot.send_message(
-                mes.chat.id,
-                "Send me the maximum points the user receive before they gets punished.",
+            let text = render(
+                &settings_repo,
+                state.chat_id,
+                TemplateKey::SetupInvalidPoints,
+                &Placeholders::new(),
             )
             .await?;
+            utils::send_with_retry(bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html))
+                .await?;
             return Ok(());
         }
     };
@@ -152,12 +231,10 @@ async fn wait_for_points_handler(
         }))
         .await?;
 
-    bot.send_message(
-        mes.chat.id,
-        "Good. Now send me a text trigger for the warn. It then will be used by `/warn <trigger>` \
-         format.",
-    )
-    .await?;
+    let text =
+        render(&settings_repo, state.chat_id, TemplateKey::SetupAskTrigger, &Placeholders::new())
+            .await?;
+    utils::send_with_retry(bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html)).await?;
 
     Ok(())
 }
@@ -167,19 +244,27 @@ async fn wait_for_trigger_handler(
     mes: Message,
     dialogue: Dialogue,
     repo: WarnsRepository,
+    settings_repo: SettingsRepository,
     state: WaitForTriggerState,
 ) -> HandlerOut {
     let trigger = match mes.text() {
         Some(t) => t.to_string(),
         None => return Ok(()),
     };
-    match repo.find_warn_by_trigger(&trigger).await? {
-        Some(_) => {
-            bot.send_message(mes.chat.id, "Warn with such trigger already exists.").await?;
-            return Ok(());
-        }
-        _ => {}
+    // `/warn` treats everything after the first word as a reason.
+    let error = if trigger.split_whitespace().count() != 1 {
+        Some(TemplateKey::SetupInvalidTrigger)
+    } else if repo.find_warn_by_trigger(state.chat_id, &trigger).await?.is_some() {
+        Some(TemplateKey::SetupTriggerExists)
+    } else {
+        None
     };
+    if let Some(key) = error {
+        let text = render(&settings_repo, state.chat_id, key, &Placeholders::new()).await?;
+        utils::send_with_retry(bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html))
+            .await?;
+        return Ok(());
+    }
 
     dialogue
         .next(SetupWarnState::WaitForOnWarn(WaitForOnWarnState {
@@ -189,30 +274,40 @@ async fn wait_for_trigger_handler(
             trigger,
         }))
         .await?;
+    let lang = settings_repo.get(state.chat_id).await?.lang();
     let kb = {
         InlineKeyboardMarkup::new([
             [InlineKeyboardButton::new(
-                "Delete message",
+                i18n::text(lang, Text::DeleteMessageButton),
                 InlineKeyboardButtonKind::CallbackData("delete".into()),
             )],
             [InlineKeyboardButton::new(
-                "Do nothing",
+                i18n::text(lang, Text::DoNothingButton),
                 InlineKeyboardButtonKind::CallbackData("nothing".into()),
             )],
         ])
     };
-    bot.send_message(
-        mes.chat.id,
-        "Good. Do you want to delete the message you reply to when warning?",
+    let text =
+        render(&settings_repo, state.chat_id, TemplateKey::SetupAskOnWarn, &Placeholders::new())
+            .await?;
+    utils::send_with_retry(
+        bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html).reply_markup(kb),
     )
-    .reply_markup(kb)
     .await?;
 
     Ok(())
 }
 
-async fn wait_for_on_warn_handler(bot: TBot, mes: Message) -> HandlerOut {
-    bot.send_message(mes.chat.id, "Please, use one of the buttons above.").await?;
+async fn wait_for_on_warn_handler(
+    bot: TBot,
+    mes: Message,
+    settings_repo: SettingsRepository,
+    state: WaitForOnWarnState,
+) -> HandlerOut {
+    let text =
+        render(&settings_repo, state.chat_id, TemplateKey::SetupUseButtons, &Placeholders::new())
+            .await?;
+    utils::send_with_retry(bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html)).await?;
     Ok(())
 }
 
@@ -221,6 +316,7 @@ pub async fn wait_for_on_warn_callback_query_handler(
     q: CallbackQuery,
     d: Dialogue,
     repo: WarnsRepository,
+    settings_repo: SettingsRepository,
 ) -> HandlerOut {
     let state = match d.current_state().await? {
         Some(SetupWarnState::WaitForOnWarn(x)) => x,
@@ -239,21 +335,40 @@ pub async fn wait_for_on_warn_callback_query_handler(
             return Ok(());
         }
     };
-    let text =
-        format!("You have added new warn type. To use it use /warn {} command", &state.trigger);
+    let placeholders = Placeholders::new().text("trigger", &state.trigger);
     d.exit().await?;
-    repo.insert_warn_type(WarningInfo {
-        trigger: state.trigger,
-        points: state.max_points,
-        group: state.group,
-        on_warn,
-    })
-    .await?;
+    // Another setup may have created a warn type with the same trigger meanwhile.
+    let inserted = repo
+        .insert_warn_type(WarningInfo {
+            id: ObjectId::new(),
+            chat_id: state.chat_id,
+            trigger: state.trigger.clone(),
+            points: state.max_points,
+            group_id: state.group.id,
+            on_warn,
+            templates: Templates::new(),
+            schema_version: SCHEMA_VERSION,
+        })
+        .await?;
+    let key = if inserted {
+        metrics::DIALOGUES.with_label_values(&["setup_warn", "completed"]).inc();
+        TemplateKey::SetupDone
+    } else {
+        TemplateKey::SetupTriggerExists
+    };
+    let text = render(&settings_repo, state.chat_id, key, &placeholders).await?;
 
-    bot.answer_callback_query(q.id).await?;
+    utils::send_with_retry(bot.answer_callback_query(q.id)).await?;
     if let Some(mes) = &q.message {
-        bot.edit_message_text(mes.chat.id, mes.id, "Selected.").await?;
-        bot.send_message(mes.chat.id, text).await?;
+        let lang = settings_repo.get(state.chat_id).await?.lang();
+        utils::send_with_retry(bot.edit_message_text(
+            mes.chat.id,
+            mes.id,
+            i18n::text(lang, Text::Selected),
+        ))
+        .await?;
+        utils::send_with_retry(bot.send_message(mes.chat.id, text).parse_mode(ParseMode::Html))
+            .await?;
     }
 
     Ok(())
@@ -1,98 +1,315 @@
 use crate::{
-    warnings::dto::{
-        OnWarnAction, Punishment, PunishmentKind, PunishmentTime, UserWarning, WarningInfo,
+    cleanup::CleanupQueue,
+    i18n::{self, Lang, Text},
+    jobs::{Job, Scheduler},
+    metrics,
+    settings::ChatSettings,
+    templates::{self, Placeholders, TemplateKey},
+    utils,
+    warnings::{
+        dto::{ActivePunishment, OnWarnAction, PunishmentKind, WarningGroup, WarningInfo},
+        policy::{self, Announcement, Effect, Penalty},
     },
     HandlerOut, Message, TBot, WarnsRepository,
 };
-use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::VecDeque;
 use teloxide::{
     prelude2::*,
-    types::{ChatPermissions, User},
+    types::{Chat, ChatPermissions, Me, ParseMode, User},
+    RequestError,
 };
 
+/// The one who is warned.
+#[derive(Debug, Clone)]
+pub(crate) enum WarnTarget {
+    User(User),
+    /// A channel that sends messages to the chat on its own behalf.
+    Channel(Chat),
+}
+
+impl WarnTarget {
+    /// Returns the target of a warn given in reply to `mes`.
+    pub(crate) fn from_message(mes: &Message) -> Option<Self> {
+        // Messages sent on behalf of a channel have a service user in `from`, so
+        // `sender_chat` must be checked first.
+        match &mes.sender_chat {
+            Some(chat) => Some(Self::Channel(chat.clone())),
+            None => mes.from().cloned().map(Self::User),
+        }
+    }
+
+    pub(crate) fn policy_target(&self) -> policy::Target {
+        match self {
+            Self::User(user) => policy::Target::User(user.id),
+            Self::Channel(chat) => policy::Target::Channel(chat.id),
+        }
+    }
+
+    /// The ID warns are stored under. Channel IDs never intersect with user IDs.
+    pub(crate) fn id(&self) -> i64 {
+        match self {
+            Self::User(user) => user.id,
+            Self::Channel(chat) => chat.id,
+        }
+    }
+
+    pub(crate) fn name(&self) -> String {
+        match self {
+            Self::User(user) => user.full_name(),
+            Self::Channel(chat) => chat.title().unwrap_or("channel").to_string(),
+        }
+    }
+
+    /// Adds the placeholder `name` that mentions the target.
+    pub(crate) fn placeholder(
+        &self,
+        name: &'static str,
+        placeholders: Placeholders,
+    ) -> Placeholders {
+        match self {
+            Self::User(user) => placeholders.mention(name, user.id, &user.full_name()),
+            Self::Channel(_) => placeholders.text(name, self.name()),
+        }
+    }
+}
+
+/// Returns names of the admin rights the bot lacks in the chat to carry out
+/// `warn`. An empty list means the bot can proceed.
+pub(crate) async fn missing_bot_rights(
+    bot: &TBot,
+    me: &Me,
+    chat_id: i64,
+    warn: &WarningInfo,
+) -> Result<Vec<Text>, RequestError> {
+    let member = utils::send_with_retry(bot.get_chat_member(chat_id, me.user.id)).await?;
+
+    let mut missing = vec![];
+    // Any warn can fill up the group, so we always need to be able to punish.
+    if !member.kind.can_restrict_members() {
+        missing.push(Text::RightBanUsers);
+    }
+    if warn.on_warn == OnWarnAction::DeleteMessage && !member.kind.can_delete_messages() {
+        missing.push(Text::RightDeleteMessages);
+    }
+    Ok(missing)
+}
+
+/// Returns the reason why `target` must not be warned in the chat, if any.
+/// Every place that warns users must check it before calling [`warn_user`].
+pub(crate) async fn protection_reason(
+    bot: &TBot,
+    me: &Me,
+    repo: &WarnsRepository,
+    chat_id: i64,
+    target: &WarnTarget,
+) -> anyhow::Result<Option<Text>> {
+    match target {
+        WarnTarget::User(user) => {
+            if user.id == me.user.id {
+                return Ok(Some(Text::ProtectedSelf));
+            }
+            let member = utils::send_with_retry(bot.get_chat_member(chat_id, user.id)).await?;
+            if member.kind.is_owner() {
+                return Ok(Some(Text::ProtectedOwner));
+            }
+            if member.kind.is_administrator() {
+                return Ok(Some(Text::ProtectedAdmin));
+            }
+        }
+        // Anonymous administrators send messages on behalf of the chat itself.
+        WarnTarget::Channel(chat) if chat.id == chat_id => {
+            return Ok(Some(Text::ProtectedAnonymousAdmin));
+        }
+        WarnTarget::Channel(_) => {}
+    }
+    if repo.is_immune(chat_id, target.id()).await? {
+        return Ok(Some(Text::ProtectedImmune));
+    }
+    Ok(None)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn warn_user(
     bot: TBot,
     mes: Message,
     repo: WarnsRepository,
-    user: User,
+    cleanup: &CleanupQueue,
+    scheduler: &Scheduler,
+    settings: &ChatSettings,
+    target: WarnTarget,
     warn: &WarningInfo,
+    group: &WarningGroup,
+    reason: &str,
 ) -> HandlerOut {
-    let points = repo.get_user_warn_points(user.id, &warn.group).await?;
-    let new_points = points + warn.points;
-    if new_points >= warn.group.max_points {
-        punish_user(bot.clone(), &mes, user.id, &warn.group.punishment).await?;
-        repo.remove_actual_warns(user.id, &warn.group).await?;
-        bot.send_message(mes.chat.id, message_user_punished(&user, &warn.group.punishment)).await?;
-    } else {
-        let text = format!(
-            "{} has been warned! {}/{} points.",
-            user.full_name(),
-            new_points,
-            warn.group.max_points
-        );
-        repo.insert_warn(UserWarning { user_id: user.id, info: warn.clone() }).await?;
-        bot.send_message(mes.chat.id, text).await?;
+    let points = repo.get_user_warn_points(target.id(), group).await?;
+    let lang = settings.lang();
+    let overrides = [&warn.templates, &group.templates, &settings.templates];
+    let placeholders = target
+        .placeholder("user", Placeholders::new())
+        .text("max", group.max_points)
+        .text("group", &group.name)
+        .text("trigger", &warn.trigger)
+        .text("reason", reason);
+
+    metrics::WARNS.with_label_values(&[&group.name, &warn.trigger]).inc();
+
+    let effects = policy::on_warn(points, target.policy_target(), warn, group, message_time(&mes));
+    let mut effects = VecDeque::from(effects);
+    let mut punish_error = None;
+    while let Some(effect) = effects.pop_front() {
+        match effect {
+            Effect::RecordWarn(user_warning) => repo.insert_warn(user_warning).await?,
+            Effect::Punish(penalty) => {
+                if let Err(err) = punish_user(&bot, &mes, &target, &penalty).await {
+                    log::error!("Failed to punish {}: {}", target.id(), err);
+                    metrics::telegram_error("punish");
+                    punish_error = Some(err);
+                    effects = policy::on_punish_failed(points, target.policy_target(), warn).into();
+                }
+            }
+            Effect::ArchiveWarns => repo.remove_actual_warns(target.id(), group).await?,
+            Effect::TrackPunishment { until } => {
+                if let WarnTarget::User(user) = &target {
+                    let punishment = ActivePunishment {
+                        chat_id: mes.chat.id,
+                        user_id: user.id,
+                        user_name: user.full_name(),
+                        group: group.name.clone(),
+                        kind: group.punishment.kind.clone(),
+                        until: until.timestamp(),
+                    };
+                    repo.set_active_punishment(&punishment).await?;
+                    let job = Job::PunishmentEnded { chat_id: mes.chat.id, user_id: user.id };
+                    scheduler.enqueue_at(job, until).await?;
+                }
+            }
+            // The job scheduled for the earlier punishment finds nothing to announce.
+            Effect::ClearPunishment => {
+                repo.remove_active_punishment(mes.chat.id, target.id()).await?
+            }
+            Effect::Announce(announcement) => {
+                let (key, placeholders) = match announcement {
+                    Announcement::Warned { points } => {
+                        (TemplateKey::Warned, placeholders.clone().text("points", points))
+                    }
+                    Announcement::Punished { points, penalty } => {
+                        let placeholders = placeholders.clone().text("points", points);
+                        (TemplateKey::Punished, penalty_placeholders(placeholders, lang, &penalty))
+                    }
+                    Announcement::PunishFailed { points } => {
+                        let error = punish_error.as_ref().map(ToString::to_string);
+                        let placeholders = placeholders
+                            .clone()
+                            .text("points", points)
+                            .text("error", error.unwrap_or_default());
+                        (TemplateKey::PunishFailed, placeholders)
+                    }
+                };
+                let text = templates::render(key, lang, &overrides, &placeholders);
+                cleanup.announce(&bot, settings, text).await?;
+            }
+        }
     }
 
     Ok(())
 }
 
+fn message_time(mes: &Message) -> DateTime<Utc> {
+    DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(mes.date as i64, 0), Utc)
+}
+
 async fn punish_user(
-    bot: TBot,
+    bot: &TBot,
     mes: &Message,
-    user_id: i64,
-    punishment: &Punishment,
-) -> HandlerOut {
-    let mes_time =
-        DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(mes.date as i64, 0), Utc);
-    let punishment_time = match punishment.time {
-        PunishmentTime::Time(d) => Duration::seconds(d as i64),
-        // If we restrict user until `now` time, telegram will restrict user forever.
-        PunishmentTime::Forever => Duration::seconds(0),
-    };
-    let until_time = mes_time + punishment_time;
+    target: &WarnTarget,
+    penalty: &Penalty,
+) -> Result<(), RequestError> {
+    if let WarnTarget::Channel(channel) = target {
+        utils::retry(|| utils::ban_chat_sender_chat(bot, mes.chat.id, channel.id)).await?;
+        metrics::PUNISHMENTS.with_label_values(&["channel_ban"]).inc();
+        return Ok(());
+    }
 
-    match punishment.kind {
+    let Penalty { kind, until } = penalty;
+    // If we restrict user until `now` time, telegram will restrict user forever.
+    let until_time = until.unwrap_or_else(|| message_time(mes));
+    let user_id = target.id();
+    match kind {
         PunishmentKind::Ban => {
-            bot.ban_chat_member(mes.chat.id, user_id)
-                .until_date(until_time.timestamp() as u64)
-                .await?;
+            utils::send_with_retry(
+                bot.ban_chat_member(mes.chat.id, user_id).until_date(until_time.timestamp() as u64),
+            )
+            .await?;
         }
         PunishmentKind::Mute => {
-            bot.restrict_chat_member(mes.chat.id, user_id, ChatPermissions::default())
-                .until_date(until_time)
-                .await?;
+            utils::send_with_retry(
+                bot.restrict_chat_member(mes.chat.id, user_id, ChatPermissions::default())
+                    .until_date(until_time),
+            )
+            .await?;
         }
         PunishmentKind::Restrict(perms) => {
-            bot.restrict_chat_member(mes.chat.id, user_id, perms).until_date(until_time).await?;
+            utils::send_with_retry(
+                bot.restrict_chat_member(mes.chat.id, user_id, perms.clone())
+                    .until_date(until_time),
+            )
+            .await?;
         }
     }
+    let kind = match kind {
+        PunishmentKind::Ban => "ban",
+        PunishmentKind::Mute => "mute",
+        PunishmentKind::Restrict(_) => "restrict",
+    };
+    metrics::PUNISHMENTS.with_label_values(&[kind]).inc();
 
     Ok(())
 }
 
-fn message_user_punished(user: &User, punishment: &Punishment) -> String {
-    let time = match punishment.time {
-        PunishmentTime::Time(s) => format!("for a {} seconds.", s),
-        PunishmentTime::Forever => format!("forever!"),
-    };
-    match punishment.kind {
-        PunishmentKind::Mute => {
-            format!("User {} has been muted {}", user.full_name(), time)
-        }
-        PunishmentKind::Ban => {
-            format!("User {} has been baned {}", user.full_name(), time)
-        }
-        PunishmentKind::Restrict(_) => {
-            format!("User {} has been restricted {}", user.full_name(), time)
-        }
+pub(crate) fn punishment_text(kind: &PunishmentKind) -> Text {
+    match kind {
+        PunishmentKind::Mute => Text::PunishmentMuted,
+        PunishmentKind::Ban => Text::PunishmentBanned,
+        PunishmentKind::Restrict(_) => Text::PunishmentRestricted,
     }
 }
 
-pub async fn on_warn(bot: TBot, reply_to_message: &Message, on_warn: OnWarnAction) -> HandlerOut {
+/// Formats the time as the `{until}` placeholder.
+pub(crate) fn until_text(lang: Lang, time: DateTime<Utc>) -> String {
+    let time = Placeholders::new().text("time", time.format("%Y-%m-%d %H:%M UTC"));
+    i18n::render(lang, Text::UntilTime, &time)
+}
+
+/// Adds `{punishment}` and `{until}` placeholders.
+fn penalty_placeholders(placeholders: Placeholders, lang: Lang, penalty: &Penalty) -> Placeholders {
+    let until = match penalty.until {
+        Some(end) => until_text(lang, end),
+        None => i18n::text(lang, Text::UntilForever).to_string(),
+    };
+    let punishment = i18n::text(lang, punishment_text(&penalty.kind));
+    placeholders.text("punishment", punishment).html("until", until)
+}
+
+pub async fn on_warn(
+    bot: TBot,
+    lang: Lang,
+    reply_to_message: &Message,
+    on_warn: OnWarnAction,
+) -> HandlerOut {
     match on_warn {
         OnWarnAction::DeleteMessage => {
-            bot.delete_message(reply_to_message.chat.id, reply_to_message.id).await?;
+            let chat_id = reply_to_message.chat.id;
+            if let Err(err) =
+                utils::send_with_retry(bot.delete_message(chat_id, reply_to_message.id)).await
+            {
+                log::error!("Failed to delete message {}: {}", reply_to_message.id, err);
+                metrics::telegram_error("delete_message");
+                let placeholders = Placeholders::new().text("error", err);
+                let text = i18n::render(lang, Text::DeleteFailed, &placeholders);
+                utils::send_with_retry(bot.send_message(chat_id, text).parse_mode(ParseMode::Html))
+                    .await?;
+            }
         }
         OnWarnAction::Nothing => {}
     }
@@ -0,0 +1,262 @@
+//! Decides what happens when someone is warned, without doing it. The rules do
+//! not touch Telegram or the database, they return [`Effect`]s which are
+//! carried out by the warn handler in order.
+
+use crate::warnings::dto::{
+    Punishment, PunishmentKind, PunishmentTime, UserWarning, WarningGroup, WarningInfo,
+};
+use chrono::{DateTime, Duration, Utc};
+
+/// The one who is warned, see `WarnTarget` of the handlers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Target {
+    User(i64),
+    Channel(i64),
+}
+
+impl Target {
+    pub(crate) fn id(self) -> i64 {
+        match self {
+            Self::User(id) | Self::Channel(id) => id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Effect {
+    /// Stores the warn among the actual warns of the target.
+    RecordWarn(UserWarning),
+    /// Punishes the target in the chat. If it fails, the rest of the effects are
+    /// replaced with [`on_punish_failed`].
+    Punish(Penalty),
+    /// Moves the actual warns of the target in the group to the old warns.
+    ArchiveWarns,
+    /// Remembers the punishment of a user until it ends, so the chat can be told
+    /// when it ends.
+    TrackPunishment {
+        until: DateTime<Utc>,
+    },
+    /// Forgets the tracked punishment of a user, so the end of an earlier
+    /// temporary one is not announced while the new one lasts forever.
+    ClearPunishment,
+    Announce(Announcement),
+}
+
+/// Channels can be neither muted nor restricted, so they are always banned
+/// forever.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Penalty {
+    pub(crate) kind: PunishmentKind,
+    /// `None` means forever.
+    pub(crate) until: Option<DateTime<Utc>>,
+}
+
+/// What to tell the chat. Points are the points of the target including the
+/// new warn.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Announcement {
+    Warned { points: u64 },
+    Punished { points: u64, penalty: Penalty },
+    PunishFailed { points: u64 },
+}
+
+/// Decides what to do when `warn` is given to the target who has `points` in
+/// the group of the warn at `now`.
+pub(crate) fn on_warn(
+    points: u64,
+    target: Target,
+    warn: &WarningInfo,
+    group: &WarningGroup,
+    now: DateTime<Utc>,
+) -> Vec<Effect> {
+    let points = points + warn.points;
+    if points < group.max_points {
+        return vec![
+            Effect::RecordWarn(UserWarning::new(target.id(), warn)),
+            Effect::Announce(Announcement::Warned { points }),
+        ];
+    }
+
+    let penalty = match target {
+        Target::User(_) => Penalty {
+            kind: group.punishment.kind.clone(),
+            until: punishment_end(&group.punishment, now),
+        },
+        Target::Channel(_) => Penalty { kind: PunishmentKind::Ban, until: None },
+    };
+    let mut effects = vec![Effect::Punish(penalty.clone()), Effect::ArchiveWarns];
+    effects.push(match penalty.until {
+        Some(until) => Effect::TrackPunishment { until },
+        None => Effect::ClearPunishment,
+    });
+    effects.push(Effect::Announce(Announcement::Punished { points, penalty }));
+    effects
+}
+
+/// Decides what to do instead of [`on_warn`] effects if the punishment failed.
+/// The warn is kept, so the target is punished on the next warn instead of
+/// losing the points.
+pub(crate) fn on_punish_failed(points: u64, target: Target, warn: &WarningInfo) -> Vec<Effect> {
+    vec![
+        Effect::RecordWarn(UserWarning::new(target.id(), warn)),
+        Effect::Announce(Announcement::PunishFailed { points: points + warn.points }),
+    ]
+}
+
+/// Returns when the punishment given at `now` ends, or `None` if it lasts
+/// forever.
+pub(crate) fn punishment_end(punishment: &Punishment, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match punishment.time {
+        PunishmentTime::Time(d) => Some(now + Duration::seconds(d as i64)),
+        PunishmentTime::Forever => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{migrations::SCHEMA_VERSION, templates::Templates, warnings::dto::OnWarnAction};
+    use chrono::TimeZone;
+    use mongodb::bson::oid::ObjectId;
+    use proptest::prelude::*;
+
+    const USER: Target = Target::User(2);
+
+    fn group(max_points: u64, time: PunishmentTime) -> WarningGroup {
+        WarningGroup {
+            id: ObjectId::new(),
+            chat_id: -1001,
+            name: "spam".to_string(),
+            max_points,
+            punishment: Punishment { time, kind: PunishmentKind::Mute },
+            templates: Templates::new(),
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+
+    fn warn(points: u64, group: &WarningGroup) -> WarningInfo {
+        WarningInfo {
+            id: ObjectId::new(),
+            chat_id: group.chat_id,
+            trigger: "ad".to_string(),
+            points,
+            group_id: group.id,
+            on_warn: OnWarnAction::Nothing,
+            templates: Templates::new(),
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.timestamp(1_641_038_400, 0)
+    }
+
+    #[test]
+    fn records_warn_below_max_points() {
+        let group = group(100, PunishmentTime::Forever);
+        let warn = warn(30, &group);
+        assert_eq!(
+            on_warn(60, USER, &warn, &group, now()),
+            vec![
+                Effect::RecordWarn(UserWarning::new(2, &warn)),
+                Effect::Announce(Announcement::Warned { points: 90 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn punishes_user_reaching_max_points() {
+        let group = group(100, PunishmentTime::Time(3600));
+        let warn = warn(40, &group);
+        let penalty =
+            Penalty { kind: PunishmentKind::Mute, until: Some(now() + Duration::hours(1)) };
+        assert_eq!(
+            on_warn(60, USER, &warn, &group, now()),
+            vec![
+                Effect::Punish(penalty.clone()),
+                Effect::ArchiveWarns,
+                Effect::TrackPunishment { until: now() + Duration::hours(1) },
+                Effect::Announce(Announcement::Punished { points: 100, penalty }),
+            ]
+        );
+    }
+
+    #[test]
+    fn forgets_earlier_punishment_when_punishing_forever() {
+        let group = group(100, PunishmentTime::Forever);
+        let warn = warn(100, &group);
+        let penalty = Penalty { kind: PunishmentKind::Mute, until: None };
+        assert_eq!(
+            on_warn(0, USER, &warn, &group, now()),
+            vec![
+                Effect::Punish(penalty.clone()),
+                Effect::ArchiveWarns,
+                Effect::ClearPunishment,
+                Effect::Announce(Announcement::Punished { points: 100, penalty }),
+            ]
+        );
+    }
+
+    #[test]
+    fn bans_channels_forever() {
+        let group = group(100, PunishmentTime::Time(3600));
+        let warn = warn(100, &group);
+        let penalty = Penalty { kind: PunishmentKind::Ban, until: None };
+        assert_eq!(
+            on_warn(0, Target::Channel(-1002), &warn, &group, now()),
+            vec![
+                Effect::Punish(penalty.clone()),
+                Effect::ArchiveWarns,
+                Effect::ClearPunishment,
+                Effect::Announce(Announcement::Punished { points: 100, penalty }),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_warn_if_punishment_failed() {
+        let group = group(100, PunishmentTime::Forever);
+        let warn = warn(50, &group);
+        assert_eq!(
+            on_punish_failed(70, USER, &warn),
+            vec![
+                Effect::RecordWarn(UserWarning::new(2, &warn)),
+                Effect::Announce(Announcement::PunishFailed { points: 120 }),
+            ]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn punishes_exactly_at_max_points(
+            points in 0u64..1000,
+            warn_points in 0u64..1000,
+            max_points in 1u64..1000,
+            secs in proptest::option::of(0u64..1_000_000),
+        ) {
+            let time = secs.map_or(PunishmentTime::Forever, PunishmentTime::Time);
+            let group = group(max_points, time);
+            let warn = warn(warn_points, &group);
+            let effects = on_warn(points, USER, &warn, &group, now());
+
+            let punished = effects.iter().any(|effect| matches!(effect, Effect::Punish(_)));
+            let recorded = effects.iter().any(|effect| matches!(effect, Effect::RecordWarn(_)));
+            prop_assert_eq!(punished, points + warn_points >= max_points);
+            // Points are either archived with the punishment or kept.
+            prop_assert_ne!(punished, recorded);
+            prop_assert_eq!(punished, effects.contains(&Effect::ArchiveWarns));
+            // The chat is told once, after everything else is done.
+            let announces =
+                effects.iter().filter(|effect| matches!(effect, Effect::Announce(_))).count();
+            prop_assert_eq!(announces, 1);
+            prop_assert!(matches!(effects.last(), Some(Effect::Announce(_))));
+        }
+
+        #[test]
+        fn punishments_end_after_they_start(secs in 0u64..100_000_000) {
+            let punishment = Punishment { time: PunishmentTime::Time(secs), kind: PunishmentKind::Ban };
+            let end = punishment_end(&punishment, now()).unwrap();
+            prop_assert_eq!((end - now()).num_seconds(), secs as i64);
+        }
+    }
+}
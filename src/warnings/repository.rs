@@ -1,7 +1,16 @@
-use crate::warnings::dto::{
-    OnWarnAction, PunishmentKind, PunishmentTime, UserWarning, WarningGroup, WarningInfo,
+use crate::{
+    utils::is_duplicate_key,
+    warnings::dto::{
+        ActivePunishment, AutoTrigger, ImmuneUser, OnWarnAction, UserWarning, WarningGroup,
+        WarningInfo,
+    },
 };
-use mongodb::{bson::doc, options::UpdateOptions, Collection, Database};
+use mongodb::{
+    bson::{self, doc, oid::ObjectId, Bson, Document},
+    options::{FindOptions, IndexOptions, ReplaceOptions, UpdateOptions},
+    Collection, Database, IndexModel,
+};
+use serde::de::DeserializeOwned;
 use teloxide::prelude::StreamExt;
 
 #[derive(Debug, Clone)]
@@ -10,6 +19,17 @@ pub struct WarnsRepository {
     actual_warns: Collection<UserWarning>,
     warning_types: Collection<WarningInfo>,
     warning_groups: Collection<WarningGroup>,
+    immune_users: Collection<ImmuneUser>,
+    auto_triggers: Collection<AutoTrigger>,
+    active_punishments: Collection<ActivePunishment>,
+}
+
+/// What happened to a stored document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Upserted {
+    Created,
+    Changed,
+    Unchanged,
 }
 
 impl WarnsRepository {
@@ -19,58 +39,229 @@ impl WarnsRepository {
             actual_warns: db.collection("actual_warns"),
             warning_types: db.collection("warning_types"),
             warning_groups: db.collection("warning_groups"),
+            immune_users: db.collection("immune_users"),
+            auto_triggers: db.collection("auto_triggers"),
+            active_punishments: db.collection("active_punishments"),
         }
     }
 
-    pub async fn insert_default_values(&self) -> Result<(), mongodb::error::Error> {
-        let mut options = UpdateOptions::default();
-        options.upsert = Some(true);
-
-        let carizm = doc! {
-            "name": "царизм",
-            "max_points": 100,
-            "punishment": {
-                "time": mongodb::bson::to_bson(&PunishmentTime::Forever).unwrap(),
-                "kind": mongodb::bson::to_bson(&PunishmentKind::Mute).unwrap(),
-            },
-        };
-
+    /// Creates indexes for the lookups below. Triggers and group names are unique
+    /// within a chat.
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        self.warning_types
+            .create_index(unique_index(doc! { "chat_id": 1, "trigger": 1 }), None)
+            .await?;
         self.warning_groups
-            .update_one(doc! { "name": "царизм" }, doc! { "$set": carizm.clone() }, options.clone())
+            .create_index(unique_index(doc! { "chat_id": 1, "name": 1 }), None)
             .await?;
-
-        self.warning_types
-            .update_one(
-                doc! { "trigger": "макака" },
-                doc! {
-                    "$set": {
-                        "trigger": "макака",
-                        "points": 30,
-                        "group": carizm.clone(),
-                        "on_warn": mongodb::bson::to_bson(&OnWarnAction::DeleteMessage).unwrap(),
-                    }
-                },
-                options.clone(),
+        self.actual_warns
+            .create_indexes(
+                [
+                    index(doc! { "group_id": 1, "user_id": 1 }),
+                    index(doc! { "chat_id": 1, "trigger": 1 }),
+                    index(doc! { "user_id": 1 }),
+                ],
+                None,
+            )
+            .await?;
+        self.immune_users
+            .create_index(unique_index(doc! { "chat_id": 1, "user_id": 1 }), None)
+            .await?;
+        self.auto_triggers.create_index(index(doc! { "chat_id": 1 }), None).await?;
+        self.active_punishments
+            .create_indexes(
+                [
+                    unique_index(doc! { "chat_id": 1, "user_id": 1 }),
+                    index(doc! { "chat_id": 1, "until": 1 }),
+                ],
+                None,
             )
             .await?;
-
         Ok(())
     }
 
-    pub async fn insert_warn_type(&self, ty: WarningInfo) -> Result<(), mongodb::error::Error> {
-        self.warning_types.insert_one(ty, None).await?;
+    /// Creates the warn group or replaces the one with the same name in the chat.
+    /// The replaced group keeps its ID.
+    pub async fn upsert_group(
+        &self,
+        mut group: WarningGroup,
+    ) -> Result<Upserted, mongodb::error::Error> {
+        let filter = doc! { "chat_id": group.chat_id, "name": &group.name };
+        match self.warning_groups.find_one(filter.clone(), None).await? {
+            None => {
+                self.warning_groups.insert_one(&group, None).await?;
+                Ok(Upserted::Created)
+            }
+            Some(old) => {
+                group.id = old.id;
+                if old == group {
+                    return Ok(Upserted::Unchanged);
+                }
+                self.warning_groups.replace_one(filter, &group, None).await?;
+                Ok(Upserted::Changed)
+            }
+        }
+    }
+
+    /// Creates the warn type or replaces the one with the same trigger in the
+    /// chat. The replaced warn type keeps its ID.
+    pub async fn upsert_warn_type(
+        &self,
+        mut ty: WarningInfo,
+    ) -> Result<Upserted, mongodb::error::Error> {
+        let filter = doc! { "chat_id": ty.chat_id, "trigger": &ty.trigger };
+        match self.warning_types.find_one(filter.clone(), None).await? {
+            None => {
+                self.warning_types.insert_one(&ty, None).await?;
+                Ok(Upserted::Created)
+            }
+            Some(old) => {
+                ty.id = old.id;
+                if old == ty {
+                    return Ok(Upserted::Unchanged);
+                }
+                self.warning_types.replace_one(filter, &ty, None).await?;
+                Ok(Upserted::Changed)
+            }
+        }
+    }
+
+    pub async fn get_auto_triggers(
+        &self,
+        chat_id: i64,
+    ) -> Result<Vec<AutoTrigger>, mongodb::error::Error> {
+        find_all(&self.auto_triggers, doc! { "chat_id": chat_id }, None).await
+    }
+
+    pub async fn replace_auto_triggers(
+        &self,
+        chat_id: i64,
+        triggers: Vec<AutoTrigger>,
+    ) -> Result<(), mongodb::error::Error> {
+        self.auto_triggers.delete_many(doc! { "chat_id": chat_id }, None).await?;
+        if !triggers.is_empty() {
+            self.auto_triggers.insert_many(triggers, None).await?;
+        }
         Ok(())
     }
 
+    pub async fn get_warn_groups(
+        &self,
+        chat_id: i64,
+    ) -> Result<Vec<WarningGroup>, mongodb::error::Error> {
+        find_all(&self.warning_groups, doc! { "chat_id": chat_id }, None).await
+    }
+
+    pub async fn get_warn_types(
+        &self,
+        chat_id: i64,
+    ) -> Result<Vec<WarningInfo>, mongodb::error::Error> {
+        find_all(&self.warning_types, doc! { "chat_id": chat_id }, None).await
+    }
+
+    /// Returns `false` if the chat already has a warn type with the trigger.
+    pub async fn insert_warn_type(&self, ty: WarningInfo) -> Result<bool, mongodb::error::Error> {
+        match self.warning_types.insert_one(ty, None).await {
+            Ok(_) => Ok(true),
+            Err(err) if is_duplicate_key(&err) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
     pub async fn insert_warn(&self, warn: UserWarning) -> Result<(), mongodb::error::Error> {
         self.actual_warns.insert_one(warn, None).await.map(|_| ())
     }
 
+    /// Changes points of the warn type. Warns given before keep their points.
+    pub async fn set_warn_type_points(
+        &self,
+        chat_id: i64,
+        trigger: &str,
+        points: u64,
+    ) -> Result<(), mongodb::error::Error> {
+        self.update_warn_type(chat_id, trigger, doc! { "$set": { "points": points as i64 } }).await
+    }
+
+    /// Moves the warn type to another group. Warns given before stay in the old
+    /// group.
+    pub async fn set_warn_type_group(
+        &self,
+        chat_id: i64,
+        trigger: &str,
+        group: &WarningGroup,
+    ) -> Result<(), mongodb::error::Error> {
+        self.update_warn_type(chat_id, trigger, doc! { "$set": { "group_id": group.id } }).await
+    }
+
+    pub async fn set_warn_type_on_warn(
+        &self,
+        chat_id: i64,
+        trigger: &str,
+        on_warn: OnWarnAction,
+    ) -> Result<(), mongodb::error::Error> {
+        let update = doc! { "$set": { "on_warn": bson::to_bson(&on_warn)? } };
+        self.update_warn_type(chat_id, trigger, update).await
+    }
+
+    async fn update_warn_type(
+        &self,
+        chat_id: i64,
+        trigger: &str,
+        update: Document,
+    ) -> Result<(), mongodb::error::Error> {
+        self.warning_types
+            .update_one(doc! { "chat_id": chat_id, "trigger": trigger }, update, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the warn type and auto triggers that give it. Active warns of the
+    /// type are moved to the old ones, so they no longer count. Returns `false`
+    /// if there is no such warn type.
+    pub async fn delete_warn_type(
+        &self,
+        chat_id: i64,
+        trigger: &str,
+    ) -> Result<bool, mongodb::error::Error> {
+        self.archive_warns(actual_warns_by_trigger_filter(chat_id, trigger)).await?;
+        self.auto_triggers
+            .delete_many(doc! { "chat_id": chat_id, "trigger": trigger }, None)
+            .await?;
+        let res = self
+            .warning_types
+            .delete_one(doc! { "chat_id": chat_id, "trigger": trigger }, None)
+            .await?;
+        Ok(res.deleted_count > 0)
+    }
+
+    /// Deletes the warn group. Active warns of the group are moved to the old
+    /// ones. Warn types of the group must be deleted first. Returns `false` if
+    /// there is no such group.
+    pub async fn delete_group(&self, group: &WarningGroup) -> Result<bool, mongodb::error::Error> {
+        self.archive_warns(doc! { "group_id": group.id }).await?;
+        let res = self.warning_groups.delete_one(doc! { "_id": group.id }, None).await?;
+        Ok(res.deleted_count > 0)
+    }
+
+    pub async fn count_actual_warns_by_trigger(
+        &self,
+        chat_id: i64,
+        trigger: &str,
+    ) -> Result<u64, mongodb::error::Error> {
+        self.actual_warns
+            .count_documents(actual_warns_by_trigger_filter(chat_id, trigger), None)
+            .await
+    }
+
     pub async fn find_warn_by_trigger(
         &self,
+        chat_id: i64,
         trigger: &str,
     ) -> Result<Option<WarningInfo>, mongodb::error::Error> {
-        let warn = self.warning_types.find_one(doc! { "trigger" : trigger }, None).await?;
+        let warn = self
+            .warning_types
+            .find_one(doc! { "chat_id": chat_id, "trigger" : trigger }, None)
+            .await?;
         let warn = warn.and_then(|w| if w.trigger == trigger { Some(w) } else { None });
         Ok(warn)
     }
@@ -81,10 +272,10 @@ impl WarnsRepository {
         group: &WarningGroup,
     ) -> Result<u64, mongodb::error::Error> {
         let sum = self
-            .get_actual_warns_by_group(user_id, &group.name)
+            .get_actual_warns_by_group(user_id, group)
             .await?
             .into_iter()
-            .map(|x| x.info.points)
+            .map(|x| x.points)
             .sum::<u64>();
         Ok(sum)
     }
@@ -94,13 +285,20 @@ impl WarnsRepository {
         user_id: i64,
         group: &WarningGroup,
     ) -> Result<(), mongodb::error::Error> {
-        // TODO: transactions.
-        let warns = self.get_actual_warns_by_group(user_id, &group.name).await?;
-        self.actual_warns
-            .delete_many(doc! { "info.group.name": &group.name, "user_id": user_id }, None)
-            .await?;
-        for warn in warns {
-            self.old_warns.insert_one(warn, None).await?;
+        self.archive_warns(actual_warns_by_group_filter(user_id, group)).await
+    }
+
+    /// Moves the actual warns to the old ones. Each warn is copied with its ID
+    /// before it is deleted, so if this fails midway it can be run again without
+    /// losing or duplicating warns.
+    async fn archive_warns(&self, filter: Document) -> Result<(), mongodb::error::Error> {
+        let actual_warns = self.actual_warns.clone_with_type::<Document>();
+        let old_warns = self.old_warns.clone_with_type::<Document>();
+        let options = ReplaceOptions::builder().upsert(true).build();
+        for warn in find_all(&actual_warns, filter, None).await? {
+            let filter = doc! { "_id": warn.get("_id").cloned() };
+            old_warns.replace_one(filter.clone(), &warn, options.clone()).await?;
+            actual_warns.delete_one(filter, None).await?;
         }
         Ok(())
     }
@@ -109,33 +307,343 @@ impl WarnsRepository {
         &self,
         user_id: i64,
     ) -> Result<Vec<UserWarning>, mongodb::error::Error> {
-        self.actual_warns
-            .find(doc! { "user_id": user_id }, None)
-            .await?
-            .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()
+        find_all(&self.actual_warns, doc! { "user_id": user_id }, None).await
     }
 
     pub async fn get_actual_warns_by_group(
         &self,
         user_id: i64,
-        group_name: &str,
+        group: &WarningGroup,
     ) -> Result<Vec<UserWarning>, mongodb::error::Error> {
-        self.actual_warns
-            .find(doc! { "info.group.name": group_name, "user_id": user_id }, None)
-            .await?
-            .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()
+        find_all(&self.actual_warns, actual_warns_by_group_filter(user_id, group), None).await
+    }
+
+    pub async fn find_warn_and_group(
+        &self,
+        chat_id: i64,
+        trigger: &str,
+    ) -> Result<Option<(WarningInfo, WarningGroup)>, mongodb::error::Error> {
+        let warn = match self.find_warn_by_trigger(chat_id, trigger).await? {
+            Some(warn) => warn,
+            None => return Ok(None),
+        };
+        match self.get_warn_group_by_id(warn.group_id).await? {
+            Some(group) => Ok(Some((warn, group))),
+            None => {
+                log::error!("Warn type `{}` refers to a missing group {}", trigger, warn.group_id);
+                Ok(None)
+            }
+        }
+    }
+
+    pub async fn get_warn_group_by_id(
+        &self,
+        id: ObjectId,
+    ) -> Result<Option<WarningGroup>, mongodb::error::Error> {
+        self.warning_groups.find_one(doc! { "_id": id }, None).await
     }
 
     pub async fn get_warn_group(
         &self,
+        chat_id: i64,
         group: &str,
     ) -> Result<Option<WarningGroup>, mongodb::error::Error> {
-        self.warning_groups.find_one(doc! { "name": group }, None).await
+        self.warning_groups.find_one(doc! { "chat_id": chat_id, "name": group }, None).await
+    }
+
+    /// Overrides the template of the warn type, or restores the default one if
+    /// `template` is `None`. Returns `false` if there is no such warn type.
+    pub async fn set_warn_type_template(
+        &self,
+        chat_id: i64,
+        trigger: &str,
+        key: &str,
+        template: Option<&str>,
+    ) -> Result<bool, mongodb::error::Error> {
+        let update = template_update(&format!("templates.{}", key), template);
+        let res = self
+            .warning_types
+            .update_one(doc! { "chat_id": chat_id, "trigger": trigger }, update, None)
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    /// Overrides the template of the warn group, or restores the default one if
+    /// `template` is `None`. Returns `false` if there is no such warn group.
+    pub async fn set_group_template(
+        &self,
+        chat_id: i64,
+        group: &str,
+        key: &str,
+        template: Option<&str>,
+    ) -> Result<bool, mongodb::error::Error> {
+        let field = format!("templates.{}", key);
+        let res = self
+            .warning_groups
+            .update_one(
+                doc! { "chat_id": chat_id, "name": group },
+                template_update(&field, template),
+                None,
+            )
+            .await?;
+        Ok(res.matched_count > 0)
+    }
+
+    pub async fn add_immune_user(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+    ) -> Result<(), mongodb::error::Error> {
+        let mut options = UpdateOptions::default();
+        options.upsert = Some(true);
+
+        self.immune_users
+            .update_one(
+                doc! { "chat_id": chat_id, "user_id": user_id },
+                doc! { "$set": { "chat_id": chat_id, "user_id": user_id } },
+                options,
+            )
+            .await?;
+        Ok(())
     }
+
+    /// Returns `false` if the user was not immune.
+    pub async fn remove_immune_user(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+    ) -> Result<bool, mongodb::error::Error> {
+        let res = self
+            .immune_users
+            .delete_one(doc! { "chat_id": chat_id, "user_id": user_id }, None)
+            .await?;
+        Ok(res.deleted_count > 0)
+    }
+
+    pub async fn is_immune(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+    ) -> Result<bool, mongodb::error::Error> {
+        let user = self
+            .immune_users
+            .find_one(doc! { "chat_id": chat_id, "user_id": user_id }, None)
+            .await?;
+        Ok(user.is_some())
+    }
+
+    /// Records the punishment, replacing the previous one of the user in the
+    /// chat.
+    pub async fn set_active_punishment(
+        &self,
+        punishment: &ActivePunishment,
+    ) -> Result<(), mongodb::error::Error> {
+        let mut options = ReplaceOptions::default();
+        options.upsert = Some(true);
+
+        self.active_punishments
+            .replace_one(
+                doc! { "chat_id": punishment.chat_id, "user_id": punishment.user_id },
+                punishment,
+                options,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_active_punishment(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+    ) -> Result<Option<ActivePunishment>, mongodb::error::Error> {
+        self.active_punishments
+            .find_one(doc! { "chat_id": chat_id, "user_id": user_id }, None)
+            .await
+    }
+
+    /// Returns punishments in the chat that end after `now`, the soonest first.
+    pub async fn get_active_punishments(
+        &self,
+        chat_id: i64,
+        now: i64,
+    ) -> Result<Vec<ActivePunishment>, mongodb::error::Error> {
+        let options = FindOptions::builder().sort(doc! { "until": 1 }).build();
+        find_all(
+            &self.active_punishments,
+            doc! { "chat_id": chat_id, "until": { "$gt": now } },
+            options,
+        )
+        .await
+    }
+
+    pub async fn remove_active_punishment(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+    ) -> Result<(), mongodb::error::Error> {
+        self.active_punishments
+            .delete_one(doc! { "chat_id": chat_id, "user_id": user_id }, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Replaces copies of warn groups and warn types embedded by older versions
+    /// of the bot with references by ID. Documents that are already migrated are
+    /// skipped, so it is safe to run more than once.
+    ///
+    /// Documents stored before the bot kept them per chat have no chat and are
+    /// moved to `legacy_chat`. If it is not given, the other documents are
+    /// migrated and an error tells how many were left.
+    pub async fn migrate_embedded_groups(&self, legacy_chat: Option<i64>) -> anyhow::Result<()> {
+        let groups = self.warning_groups.clone_with_type::<Document>();
+        let types = self.warning_types.clone_with_type::<Document>();
+        let mut left = 0;
+
+        let without_chat = doc! { "chat_id": { "$exists": false } };
+        for collection in [&groups, &types] {
+            match legacy_chat {
+                Some(chat_id) => {
+                    collection
+                        .update_many(
+                            without_chat.clone(),
+                            doc! { "$set": { "chat_id": chat_id } },
+                            None,
+                        )
+                        .await?;
+                }
+                None => left += collection.count_documents(without_chat.clone(), None).await?,
+            }
+        }
+
+        let legacy_types = find_all(&types, doc! { "group": { "$exists": true } }, None).await?;
+        for ty in legacy_types {
+            let chat_id = match chat_of(&ty, legacy_chat) {
+                Some(chat_id) => chat_id,
+                // Counted with the stored warn types above.
+                None => continue,
+            };
+            let group_id = self.migrate_group(&groups, ty.get_document("group")?, chat_id).await?;
+            types
+                .update_one(
+                    doc! { "_id": ty.get_object_id("_id")? },
+                    doc! { "$set": { "group_id": group_id }, "$unset": { "group": "" } },
+                    None,
+                )
+                .await?;
+        }
+
+        for warns in [&self.actual_warns, &self.old_warns] {
+            let warns = warns.clone_with_type::<Document>();
+            let legacy_warns = find_all(&warns, doc! { "info": { "$exists": true } }, None).await?;
+            for warn in legacy_warns {
+                let info = warn.get_document("info")?;
+                let chat_id = match chat_of(info, legacy_chat) {
+                    Some(chat_id) => chat_id,
+                    None => {
+                        left += 1;
+                        continue;
+                    }
+                };
+                let trigger = info.get_str("trigger")?;
+                let group_id =
+                    self.migrate_group(&groups, info.get_document("group")?, chat_id).await?;
+                let warn_type_id = self.find_warn_by_trigger(chat_id, trigger).await?.map(|t| t.id);
+                let update = doc! {
+                    "$set": {
+                        "chat_id": chat_id,
+                        "warn_type_id": warn_type_id,
+                        "trigger": trigger,
+                        "points": get_int(info, "points")?,
+                        "group_id": group_id,
+                    },
+                    "$unset": { "info": "" },
+                };
+                warns.update_one(doc! { "_id": warn.get_object_id("_id")? }, update, None).await?;
+            }
+        }
+
+        if left > 0 {
+            anyhow::bail!(
+                "{} warns, warn types and groups were stored before the bot kept them per chat, \
+                 set {} to the chat they belong to",
+                left,
+                crate::app_config::LEGACY_CHAT_ENV
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the ID of the stored group the embedded copy was made of. The
+    /// group is restored from the copy if it was deleted.
+    async fn migrate_group(
+        &self,
+        groups: &Collection<Document>,
+        group: &Document,
+        owner_chat_id: i64,
+    ) -> anyhow::Result<ObjectId> {
+        // Copies made before the bot kept groups per chat belong to the chat of
+        // the warn type or warn they are embedded in.
+        let chat_id = group.get_i64("chat_id").unwrap_or(owner_chat_id);
+        let filter = doc! { "chat_id": chat_id, "name": group.get_str("name")? };
+        if let Some(stored) = groups.find_one(filter, None).await? {
+            return Ok(stored.get_object_id("_id")?);
+        }
+        let id = ObjectId::new();
+        let mut group = group.clone();
+        group.insert("_id", id);
+        group.insert("chat_id", chat_id);
+        groups.insert_one(group, None).await?;
+        Ok(id)
+    }
+}
+
+/// The chat of a document written by an older version of the bot, or the
+/// legacy chat if it was stored before the bot kept documents per chat.
+fn chat_of(document: &Document, legacy_chat: Option<i64>) -> Option<i64> {
+    document.get_i64("chat_id").ok().or(legacy_chat)
+}
+
+/// Reads an integer stored either as Int32, like by the first versions of the
+/// bot, or as Int64.
+fn get_int(document: &Document, key: &str) -> anyhow::Result<i64> {
+    match document.get(key) {
+        Some(Bson::Int32(value)) => Ok((*value).into()),
+        Some(Bson::Int64(value)) => Ok(*value),
+        _ => Err(anyhow::anyhow!("`{}` is not an integer in {}", key, document)),
+    }
+}
+
+async fn find_all<T>(
+    collection: &Collection<T>,
+    filter: Document,
+    options: impl Into<Option<FindOptions>>,
+) -> Result<Vec<T>, mongodb::error::Error>
+where
+    T: DeserializeOwned + Unpin + Send + Sync,
+{
+    collection.find(filter, options).await?.collect::<Vec<_>>().await.into_iter().collect()
+}
+
+fn template_update(field: &str, template: Option<&str>) -> Document {
+    match template {
+        Some(template) => doc! { "$set": { field: template } },
+        None => doc! { "$unset": { field: "" } },
+    }
+}
+
+fn actual_warns_by_trigger_filter(chat_id: i64, trigger: &str) -> Document {
+    doc! { "chat_id": chat_id, "trigger": trigger }
+}
+
+fn actual_warns_by_group_filter(user_id: i64, group: &WarningGroup) -> Document {
+    doc! { "group_id": group.id, "user_id": user_id }
+}
+
+fn index(keys: Document) -> IndexModel {
+    IndexModel::builder().keys(keys).build()
+}
+
+fn unique_index(keys: Document) -> IndexModel {
+    let options = IndexOptions::builder().unique(true).build();
+    IndexModel::builder().keys(keys).options(options).build()
 }
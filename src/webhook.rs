@@ -0,0 +1,77 @@
+//! Receiving updates with a webhook instead of long polling.
+//!
+//! The bot listens for `POST /<secret>` requests with update JSON in the body
+//! and registers `<url>/<secret>` as its webhook, so a reverse proxy should
+//! forward requests under `<url>` to the root of the listener. Requests to
+//! other paths are rejected, so only Telegram (and whoever knows the secret)
+//! can send updates. For a local test, POST an update to the listener:
+//!
+//! ```sh
+//! curl -X POST -H 'Content-Type: application/json' -d @update.json \
+//!     http://127.0.0.1:8080/<secret>
+//! ```
+
+use crate::app_config::WebhookConfig;
+use std::convert::Infallible;
+use teloxide::{
+    dispatching::{
+        stop_token::AsyncStopToken,
+        update_listeners::{StatefulListener, UpdateListener},
+    },
+    requests::{Request, Requester},
+    types::Update,
+    Bot,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use warp::{http::StatusCode, hyper::body::Bytes, Filter};
+
+/// Registers the webhook and starts the HTTP listener for it.
+pub async fn listener(
+    bot: &Bot,
+    config: &WebhookConfig,
+) -> anyhow::Result<impl UpdateListener<Infallible>> {
+    let url = format!("{}/{}", config.url.trim_end_matches('/'), config.secret);
+    bot.set_webhook(url).send().await?;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let secret = config.secret.clone();
+    let route = warp::post()
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::body::bytes())
+        .map(move |token: String, body: Bytes| {
+            if !secrets_equal(&token, &secret) {
+                return StatusCode::NOT_FOUND;
+            }
+            match serde_json::from_slice::<Update>(&body) {
+                Ok(update) => {
+                    // The receiver is dropped only when the dispatcher stops.
+                    let _ = tx.send(Ok(update));
+                }
+                // Telegram resends updates which are not answered with 200, so a
+                // malformed update would be sent again and again.
+                Err(err) => log::error!("Cannot parse an update from the webhook: {}", err),
+            }
+            StatusCode::OK
+        });
+
+    let (stop_token, stop_flag) = AsyncStopToken::new_pair();
+    let (addr, server) =
+        warp::serve(route).try_bind_with_graceful_shutdown(config.addr, stop_flag)?;
+    log::info!("Listening for webhook updates on {}", addr);
+    tokio::spawn(server);
+
+    let stream = UnboundedReceiverStream::new(rx);
+    fn stream_of<S, T>(state: &mut (S, T)) -> &mut S {
+        &mut state.0
+    }
+    Ok(StatefulListener::new((stream, stop_token), stream_of, |state: &mut (_, AsyncStopToken)| {
+        state.1.clone()
+    }))
+}
+
+/// Compares secrets in time that does not depend on where they differ.
+fn secrets_equal(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,28 @@
+mod common;
+
+use common::{run_admin, test_database, CHAT_ID};
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn exports_chat_without_warn_types() {
+    let db = test_database().await;
+    let chat = CHAT_ID.to_string();
+    run_admin(&db, None, &["create-group", &chat, "spam", "100", "ban", "forever"]).await.unwrap();
+
+    let document = run_admin(&db, None, &["export", &chat]).await.unwrap();
+    let config: toml::Value = toml::from_str(&document).unwrap();
+    let chat_config = &config["chats"][0];
+    assert_eq!(chat_config["id"].as_integer(), Some(CHAT_ID));
+    assert_eq!(chat_config["groups"][0]["name"].as_str(), Some("spam"));
+    assert!(chat_config.get("warn_types").is_none(), "{}", document);
+    assert!(chat_config.get("auto_triggers").is_none(), "{}", document);
+
+    // The export is a valid configuration file.
+    let path = std::env::temp_dir().join(format!("{}.toml", db.name()));
+    std::fs::write(&path, &document).unwrap();
+    let report = run_admin(&db, None, &["import", path.to_str().unwrap()]).await;
+    std::fs::remove_file(&path).unwrap();
+    assert!(report.unwrap().starts_with("Synced the configuration"));
+
+    db.drop(None).await.unwrap();
+}
@@ -0,0 +1,271 @@
+//! A local HTTP server that answers Bot API requests like Telegram would and
+//! records them, so tests can check what the bot did.
+
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use warp::{hyper::body::Bytes, Filter};
+
+pub const BOT_ID: i64 = 100;
+pub const BOT_USERNAME: &str = "autowarn_test_bot";
+
+/// How long to wait for the bot to make a request.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A request the bot made.
+#[derive(Debug, Clone)]
+pub struct Call {
+    /// The method as the bot sent it, e.g. `SendMessage`. Telegram ignores the
+    /// case of method names, so compare them with [`Call::is`].
+    pub method: String,
+    pub params: Value,
+    /// What the fake API answered.
+    pub response: Value,
+}
+
+impl Call {
+    pub fn is(&self, method: &str) -> bool {
+        self.method.eq_ignore_ascii_case(method)
+    }
+}
+
+/// Status of a user in a chat, returned by `getChatMember`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberStatus {
+    Owner,
+    Admin,
+    Member,
+}
+
+#[derive(Default)]
+struct State {
+    calls: Vec<Call>,
+    /// Indexes of calls already returned by [`FakeApi::wait_for`].
+    taken: Vec<bool>,
+    members: HashMap<(i64, i64), MemberStatus>,
+    /// Responses to return instead of the usual ones, by lowercase method.
+    scripted: HashMap<String, VecDeque<Value>>,
+}
+
+#[derive(Clone)]
+pub struct FakeApi {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+impl FakeApi {
+    /// Starts the server on a free local port.
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let message_ids = Arc::new(AtomicI32::new(1000));
+        let route = {
+            let state = state.clone();
+            warp::post()
+                .and(warp::path::param::<String>())
+                .and(warp::path::param::<String>())
+                .and(warp::path::end())
+                .and(warp::body::bytes())
+                .map(move |_token: String, method: String, body: Bytes| {
+                    let params = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                    let response = respond(&state, &message_ids, &method, &params);
+                    let mut state = state.lock().unwrap();
+                    state.calls.push(Call { method, params, response: response.clone() });
+                    state.taken.push(false);
+                    warp::reply::json(&response)
+                })
+        };
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        Self { addr, state }
+    }
+
+    /// The URL to pass to `Bot::set_api_url`.
+    pub fn url(&self) -> reqwest::Url {
+        reqwest::Url::parse(&format!("http://{}", self.addr)).unwrap()
+    }
+
+    /// Users are members of chats unless set otherwise. The bot is an
+    /// administrator with all rights.
+    pub fn set_member(&self, chat_id: i64, user_id: i64, status: MemberStatus) {
+        self.state.lock().unwrap().members.insert((chat_id, user_id), status);
+    }
+
+    /// Makes the next call of the method return `response` instead of the
+    /// usual result, e.g. an error.
+    pub fn respond_next(&self, method: &str, response: Value) {
+        let mut state = self.state.lock().unwrap();
+        state.scripted.entry(method.to_lowercase()).or_default().push_back(response);
+    }
+
+    /// All calls made so far.
+    pub fn calls(&self) -> Vec<Call> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Waits for a call of the method which was not returned before, and
+    /// returns it. Panics if the bot does not make it in time.
+    pub async fn wait_for(&self, method: &str) -> Call {
+        let wait = async {
+            loop {
+                {
+                    let mut state = self.state.lock().unwrap();
+                    let State { calls, taken, .. } = &mut *state;
+                    let found =
+                        calls.iter().zip(taken.iter_mut()).find(|(c, t)| !**t && c.is(method));
+                    if let Some((call, taken)) = found {
+                        *taken = true;
+                        return call.clone();
+                    }
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        };
+        match tokio::time::timeout(WAIT_TIMEOUT, wait).await {
+            Ok(call) => call,
+            Err(_) => panic!("the bot did not call {}, calls: {:#?}", method, self.calls()),
+        }
+    }
+
+    /// Waits for a message sent to the chat and returns its text.
+    pub async fn wait_for_message(&self, chat_id: i64) -> String {
+        let call = self.wait_for("sendMessage").await;
+        assert_eq!(call.params["chat_id"], chat_id, "unexpected message: {:?}", call);
+        call.params["text"].as_str().unwrap().to_string()
+    }
+
+    /// Returns whether the bot called the method at all.
+    pub fn was_called(&self, method: &str) -> bool {
+        self.calls().iter().any(|call| call.is(method))
+    }
+}
+
+fn respond(state: &Mutex<State>, message_ids: &AtomicI32, method: &str, params: &Value) -> Value {
+    let method = method.to_lowercase();
+    let scripted = state.lock().unwrap().scripted.get_mut(&method).and_then(VecDeque::pop_front);
+    if let Some(response) = scripted {
+        return response;
+    }
+    let result = match method.as_str() {
+        "getme" => json!({
+            "id": BOT_ID,
+            "is_bot": true,
+            "first_name": "Autowarn",
+            "username": BOT_USERNAME,
+            "can_join_groups": true,
+            "can_read_all_group_messages": false,
+            "supports_inline_queries": false,
+        }),
+        "getchatmember" => {
+            let chat_id = params["chat_id"].as_i64().unwrap();
+            let user_id = params["user_id"].as_i64().unwrap();
+            let status = if user_id == BOT_ID {
+                MemberStatus::Admin
+            } else {
+                let status = state.lock().unwrap().members.get(&(chat_id, user_id)).copied();
+                status.unwrap_or(MemberStatus::Member)
+            };
+            chat_member(user_id, status)
+        }
+        "sendmessage" | "editmessagetext" => {
+            let id = match params["message_id"].as_i64() {
+                Some(id) => id as i32,
+                None => message_ids.fetch_add(1, Ordering::Relaxed),
+            };
+            let mut mes = message(id, params["chat_id"].as_i64().unwrap(), &bot_user());
+            mes["text"] = params["text"].clone();
+            if !params["reply_markup"].is_null() {
+                mes["reply_markup"] = params["reply_markup"].clone();
+            }
+            mes
+        }
+        "setmycommands"
+        | "deletemycommands"
+        | "deletemessage"
+        | "answercallbackquery"
+        | "banchatmember"
+        | "banchatsenderchat"
+        | "restrictchatmember"
+        | "unbanchatmember"
+        | "deletewebhook"
+        | "setwebhook" => json!(true),
+        _ => {
+            return json!({
+                "ok": false,
+                "error_code": 400,
+                "description": format!("Bad Request: the fake API does not support {}", method),
+            })
+        }
+    };
+    json!({ "ok": true, "result": result })
+}
+
+/// An error response telling the bot to wait before the next request.
+pub fn retry_after(secs: u32) -> Value {
+    json!({
+        "ok": false,
+        "error_code": 429,
+        "description": format!("Too Many Requests: retry after {}", secs),
+        "parameters": { "retry_after": secs },
+    })
+}
+
+pub fn user(id: i64, first_name: &str) -> Value {
+    json!({ "id": id, "is_bot": false, "first_name": first_name })
+}
+
+pub fn bot_user() -> Value {
+    json!({ "id": BOT_ID, "is_bot": true, "first_name": "Autowarn", "username": BOT_USERNAME })
+}
+
+/// Private chats have the ID of the user, group chats have negative IDs.
+pub fn chat(id: i64) -> Value {
+    if id > 0 {
+        json!({ "id": id, "type": "private", "first_name": "User" })
+    } else {
+        json!({ "id": id, "type": "supergroup", "title": "Test chat" })
+    }
+}
+
+pub fn channel(id: i64) -> Value {
+    json!({ "id": id, "type": "channel", "title": "Test channel" })
+}
+
+/// A message without content.
+pub fn message(id: i32, chat_id: i64, from: &Value) -> Value {
+    json!({
+        "message_id": id,
+        "date": chrono::Utc::now().timestamp(),
+        "chat": chat(chat_id),
+        "from": from,
+    })
+}
+
+fn chat_member(user_id: i64, status: MemberStatus) -> Value {
+    let user = if user_id == BOT_ID { bot_user() } else { user(user_id, "User") };
+    match status {
+        MemberStatus::Owner => json!({ "user": user, "status": "creator", "is_anonymous": false }),
+        MemberStatus::Admin => json!({
+            "user": user,
+            "status": "administrator",
+            "is_anonymous": false,
+            "can_be_edited": false,
+            "can_manage_chat": true,
+            "can_change_info": true,
+            "can_delete_messages": true,
+            "can_manage_voice_chats": true,
+            "can_invite_users": true,
+            "can_restrict_members": true,
+            "can_pin_messages": true,
+            "can_promote_members": true,
+        }),
+        MemberStatus::Member => json!({ "user": user, "status": "member" }),
+    }
+}
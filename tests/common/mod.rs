@@ -0,0 +1,209 @@
+//! A harness for end-to-end tests. The bot runs against [`FakeApi`] instead of
+//! Telegram and a throwaway database on the MongoDB server given in the
+//! `AUTOWARN_TEST_MONGO` env variable. The tests are ignored by default, run
+//! them with `cargo test -- --ignored`.
+#![allow(dead_code)]
+
+mod fake_api;
+
+pub use fake_api::{retry_after, user, Call, FakeApi, MemberStatus, BOT_ID, BOT_USERNAME};
+
+use mongodb::{bson::oid::ObjectId, Client, Database};
+use roff_bot::{admin, setup_dispatcher, AppConfig, Health};
+use serde_json::{json, Value};
+use std::{convert::Infallible, path::PathBuf, sync::Arc, time::Duration};
+use teloxide::{
+    dispatching::{stop_token::AsyncStopToken, update_listeners::StatefulListener},
+    prelude2::*,
+    types::Update,
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+/// Connection string of the MongoDB server for tests.
+pub const TEST_MONGO_ENV: &str = "AUTOWARN_TEST_MONGO";
+
+pub const CHAT_ID: i64 = -1001;
+pub const OWNER_ID: i64 = 1;
+pub const USER_ID: i64 = 2;
+/// The user Telegram shows as the sender of anonymous administrators' messages.
+pub const ANONYMOUS_ADMIN_ID: i64 = 1087968824;
+/// A channel that sends messages to the chat on its own behalf.
+pub const CHANNEL_ID: i64 = -1002;
+/// The user Telegram shows as the sender of channels' messages.
+pub const CHANNEL_BOT_ID: i64 = 136817688;
+
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Returns a throwaway database on the MongoDB server for tests. Drop it at
+/// the end of the test.
+pub async fn test_database() -> Database {
+    let (mongo_options, database) = test_database_name();
+    connect(&mongo_options, &database).await
+}
+
+/// Runs a command of the admin tool.
+pub async fn run_admin(
+    db: &Database,
+    legacy_chat: Option<i64>,
+    args: &[&str],
+) -> anyhow::Result<String> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    admin::run(db, legacy_chat, &args).await
+}
+
+fn test_database_name() -> (String, String) {
+    let options = std::env::var(TEST_MONGO_ENV).unwrap_or_else(|_| {
+        panic!("set {} to a MongoDB connection string to run this test", TEST_MONGO_ENV)
+    });
+    (options, format!("autowarn_test_{}", ObjectId::new()))
+}
+
+async fn connect(mongo_options: &str, database: &str) -> Database {
+    Client::with_uri_str(mongo_options).await.unwrap().database(database)
+}
+
+/// A running bot. Call [`TestBot::stop`] at the end of the test to drop its
+/// database.
+pub struct TestBot {
+    pub api: FakeApi,
+    pub db: Database,
+    chats_config: PathBuf,
+    updates: mpsc::UnboundedSender<Result<Update, Infallible>>,
+    dispatcher: JoinHandle<()>,
+    next_update_id: i32,
+    next_message_id: i32,
+}
+
+impl TestBot {
+    /// Starts the bot with the configuration of chats in TOML. [`OWNER_ID`]
+    /// owns [`CHAT_ID`].
+    pub async fn start(chats_config: &str) -> Self {
+        let (mongo_options, database) = test_database_name();
+        let chats_config_path = std::env::temp_dir().join(format!("{}.toml", database));
+        std::fs::write(&chats_config_path, chats_config).unwrap();
+
+        let api = FakeApi::start().await;
+        api.set_member(CHAT_ID, OWNER_ID, MemberStatus::Owner);
+        // The owner sets the bot up in private messages.
+        api.set_member(OWNER_ID, OWNER_ID, MemberStatus::Owner);
+
+        let config = AppConfig {
+            token: "123:test".to_string(),
+            mongo_options,
+            database,
+            legacy_chat: None,
+            chats_config: Some(chats_config_path.clone()),
+            operators: vec![],
+            status_addr: None,
+            record: None,
+            webhook: None,
+        };
+        let db = connect(&config.mongo_options, &config.database).await;
+        let bot = Bot::new(&config.token).set_api_url(api.url());
+        let mut dispatcher =
+            setup_dispatcher(bot, db.clone(), &config, Arc::new(Health::default())).await.unwrap();
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let dispatcher = tokio::spawn(async move {
+            // The stream ends when the test bot is stopped.
+            let (stop_token, _) = AsyncStopToken::new_pair();
+            let stream = UnboundedReceiverStream::new(rx);
+            fn stream_of<S, T>(state: &mut (S, T)) -> &mut S {
+                &mut state.0
+            }
+            let listener = StatefulListener::new(
+                (stream, stop_token),
+                stream_of,
+                |state: &mut (_, AsyncStopToken)| state.1.clone(),
+            );
+            let error_handler = LoggingErrorHandler::with_custom_text("An error from the listener");
+            dispatcher.dispatch_with_listener(listener, error_handler).await;
+        });
+
+        Self {
+            api,
+            db,
+            chats_config: chats_config_path,
+            updates: tx,
+            dispatcher,
+            next_update_id: 1,
+            next_message_id: 1,
+        }
+    }
+
+    /// Sends a message from the user to the chat and returns its ID.
+    pub fn send_message(&mut self, chat_id: i64, from: i64, text: &str) -> i32 {
+        let mes = self.message(chat_id, &user(from, "User"), text);
+        let id = mes["message_id"].as_i64().unwrap() as i32;
+        self.send_update(json!({ "message": mes }));
+        id
+    }
+
+    /// Sends a message from the user in reply to a message of `target` and
+    /// returns the ID of the replied message.
+    pub fn send_reply(&mut self, chat_id: i64, from: i64, target: i64, text: &str) -> i32 {
+        let replied = self.message(chat_id, &user(target, "Target"), "Hello");
+        let replied_id = replied["message_id"].as_i64().unwrap() as i32;
+        let mut mes = self.message(chat_id, &user(from, "User"), text);
+        mes["reply_to_message"] = replied;
+        self.send_update(json!({ "message": mes }));
+        replied_id
+    }
+
+    /// Sends a message an anonymous administrator sent in reply to a message of
+    /// `target`.
+    pub fn send_anonymous_reply(&mut self, target: i64, text: &str) {
+        let replied = self.message(CHAT_ID, &user(target, "Target"), "Hello");
+        let mut mes = self.message(CHAT_ID, &user(ANONYMOUS_ADMIN_ID, "Group"), text);
+        mes["sender_chat"] = fake_api::chat(CHAT_ID);
+        mes["reply_to_message"] = replied;
+        self.send_update(json!({ "message": mes }));
+    }
+
+    /// Sends a message from the user in reply to a message the channel sent on
+    /// its own behalf.
+    pub fn send_reply_to_channel(&mut self, from: i64, text: &str) {
+        let mut replied = self.message(CHAT_ID, &user(CHANNEL_BOT_ID, "Channel"), "Hello");
+        replied["sender_chat"] = fake_api::channel(CHANNEL_ID);
+        let mut mes = self.message(CHAT_ID, &user(from, "User"), text);
+        mes["reply_to_message"] = replied;
+        self.send_update(json!({ "message": mes }));
+    }
+
+    /// Presses a button under a message the bot sent with `sent`.
+    pub fn press_button(&mut self, from: i64, sent: &Call, data: &str) {
+        let query = json!({
+            "id": self.next_update_id.to_string(),
+            "from": user(from, "User"),
+            "message": sent.response["result"],
+            "chat_instance": "test",
+            "data": data,
+        });
+        self.send_update(json!({ "callback_query": query }));
+    }
+
+    /// Stops the dispatcher and drops the database.
+    pub async fn stop(self) {
+        drop(self.updates);
+        if tokio::time::timeout(STOP_TIMEOUT, self.dispatcher).await.is_err() {
+            panic!("the dispatcher did not stop");
+        }
+        self.db.drop(None).await.unwrap();
+        std::fs::remove_file(&self.chats_config).unwrap();
+    }
+
+    fn message(&mut self, chat_id: i64, from: &Value, text: &str) -> Value {
+        let mut mes = fake_api::message(self.next_message_id, chat_id, from);
+        mes["text"] = json!(text);
+        self.next_message_id += 1;
+        mes
+    }
+
+    fn send_update(&mut self, mut update: Value) {
+        update["update_id"] = json!(self.next_update_id);
+        self.next_update_id += 1;
+        let update = serde_json::from_value(update).unwrap();
+        self.updates.send(Ok(update)).unwrap();
+    }
+}
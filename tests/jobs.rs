@@ -0,0 +1,41 @@
+mod common;
+
+use common::TestBot;
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use std::time::Duration;
+
+const CONFIG: &str = r#"
+[[chats]]
+id = -1001
+"#;
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn marks_unreadable_jobs_as_failed() {
+    let bot = TestBot::start(CONFIG).await;
+    let jobs = bot.db.collection::<Document>("jobs");
+    let id = ObjectId::new();
+    let job = doc! {
+        "_id": id,
+        "job": { "kind": "Unknown" },
+        "run_at": 0_i64,
+        "attempts": 0,
+        "failed": false,
+    };
+    jobs.insert_one(job, None).await.unwrap();
+
+    let failed = async {
+        loop {
+            let job = jobs.find_one(doc! { "_id": id }, None).await.unwrap().unwrap();
+            if job.get_bool("failed").unwrap() {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    };
+    let job = tokio::time::timeout(Duration::from_secs(15), failed).await.unwrap();
+    assert_eq!(job.get_i32("attempts").unwrap(), 1);
+    assert!(job.get_str("last_error").unwrap().starts_with("cannot read the job"));
+
+    bot.stop().await;
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{run_admin, test_database, CHAT_ID, USER_ID};
+use mongodb::{
+    bson::{doc, Document},
+    Database,
+};
+
+/// A warn group as the first versions of the bot stored it: without a chat and
+/// with Int32 numbers.
+fn baseline_group() -> Document {
+    doc! {
+        "name": "spam",
+        "max_points": 100,
+        "punishment": { "time": "Forever", "kind": "Mute" },
+    }
+}
+
+/// Seeds the database with a warn group, a warn type and a warn stored by the
+/// first versions of the bot.
+async fn seed_baseline(db: &Database) {
+    db.collection::<Document>("warning_groups").insert_one(baseline_group(), None).await.unwrap();
+    let ty = doc! {
+        "trigger": "ad",
+        "points": 30,
+        "group": baseline_group(),
+        "on_warn": "DeleteMessage",
+    };
+    db.collection::<Document>("warning_types").insert_one(ty.clone(), None).await.unwrap();
+    let warn = doc! { "user_id": USER_ID, "info": ty };
+    db.collection::<Document>("actual_warns").insert_one(warn, None).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn migrates_baseline_documents_to_legacy_chat() {
+    let db = test_database().await;
+    seed_baseline(&db).await;
+
+    run_admin(&db, Some(CHAT_ID), &["migrate"]).await.unwrap();
+    let groups = run_admin(&db, None, &["groups", &CHAT_ID.to_string()]).await.unwrap();
+    assert_eq!(groups, "spam: 100 points, mute forever\n");
+    let types = run_admin(&db, None, &["types", &CHAT_ID.to_string()]).await.unwrap();
+    assert_eq!(types, "ad: 30 points in `spam`, deletes the message\n");
+    let warns =
+        run_admin(&db, None, &["warns", &CHAT_ID.to_string(), &USER_ID.to_string()]).await.unwrap();
+    assert_eq!(warns, "spam: 30/100 points\n  ad (30)\n");
+
+    db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn asks_for_legacy_chat_to_migrate_baseline_documents() {
+    let db = test_database().await;
+    seed_baseline(&db).await;
+
+    let err = run_admin(&db, None, &["migrate"]).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("AUTOWARN_LEGACY_CHAT"), "{:#}", err);
+    // Nothing is recorded, so the migration runs again once the chat is given.
+    run_admin(&db, Some(CHAT_ID), &["migrate"]).await.unwrap();
+    let groups = run_admin(&db, None, &["groups", &CHAT_ID.to_string()]).await.unwrap();
+    assert_eq!(groups, "spam: 100 points, mute forever\n");
+
+    db.drop(None).await.unwrap();
+}
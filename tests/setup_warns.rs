@@ -0,0 +1,67 @@
+mod common;
+
+use common::{TestBot, CHAT_ID, OWNER_ID, USER_ID};
+
+const CONFIG: &str = r#"
+[[chats]]
+id = -1001
+
+[[chats.groups]]
+name = "spam"
+max_points = 100
+punishment = { kind = "ban", time = "forever" }
+"#;
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn creates_warn_type_with_dialogue() {
+    let mut bot = TestBot::start(CONFIG).await;
+
+    bot.send_message(OWNER_ID, OWNER_ID, &format!("/newwarn {}", CHAT_ID));
+    bot.api.wait_for_message(OWNER_ID).await;
+    bot.send_message(OWNER_ID, OWNER_ID, "spam");
+    bot.api.wait_for_message(OWNER_ID).await;
+    bot.send_message(OWNER_ID, OWNER_ID, "50");
+    bot.api.wait_for_message(OWNER_ID).await;
+    bot.send_message(OWNER_ID, OWNER_ID, "flood");
+    let question = bot.api.wait_for("sendMessage").await;
+    assert!(!question.params["reply_markup"].is_null());
+
+    bot.press_button(OWNER_ID, &question, "nothing");
+    bot.api.wait_for("answerCallbackQuery").await;
+    bot.api.wait_for("editMessageText").await;
+    let text = bot.api.wait_for_message(OWNER_ID).await;
+    assert!(text.contains("/warn flood"), "{}", text);
+
+    bot.send_reply(CHAT_ID, OWNER_ID, USER_ID, "/warn flood");
+    let text = bot.api.wait_for_message(CHAT_ID).await;
+    assert!(text.contains("50/100"), "{}", text);
+    assert!(!bot.api.was_called("deleteMessage"));
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn asks_again_for_invalid_points() {
+    let mut bot = TestBot::start(CONFIG).await;
+
+    bot.send_message(OWNER_ID, OWNER_ID, &format!("/newwarn {}", CHAT_ID));
+    bot.api.wait_for_message(OWNER_ID).await;
+    bot.send_message(OWNER_ID, OWNER_ID, "spam");
+    let ask_points = bot.api.wait_for_message(OWNER_ID).await;
+    bot.send_message(OWNER_ID, OWNER_ID, "many");
+    let invalid = bot.api.wait_for_message(OWNER_ID).await;
+    assert_ne!(invalid, ask_points);
+    bot.send_message(OWNER_ID, OWNER_ID, "/cancel");
+    bot.api.wait_for_message(OWNER_ID).await;
+
+    // The setup is over, so the text is not taken as points.
+    bot.send_message(OWNER_ID, OWNER_ID, "50");
+    bot.send_message(OWNER_ID, OWNER_ID, "/cancel");
+    bot.api.wait_for_message(OWNER_ID).await;
+    let messages = bot.api.calls().into_iter().filter(|call| call.is("sendMessage")).count();
+    assert_eq!(messages, 5);
+
+    bot.stop().await;
+}
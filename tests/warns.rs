@@ -0,0 +1,127 @@
+mod common;
+
+use common::{retry_after, TestBot, CHANNEL_ID, CHAT_ID, OWNER_ID, USER_ID};
+
+const CONFIG: &str = r#"
+[[chats]]
+id = -1001
+
+[[chats.groups]]
+name = "spam"
+max_points = 100
+punishment = { kind = "mute", time = 3600 }
+
+[[chats.warn_types]]
+trigger = "ad"
+points = 60
+group = "spam"
+on_warn = "delete_message"
+"#;
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn warns_replied_user() {
+    let mut bot = TestBot::start(CONFIG).await;
+
+    let replied_id = bot.send_reply(CHAT_ID, OWNER_ID, USER_ID, "/warn ad links in bio");
+    let text = bot.api.wait_for_message(CHAT_ID).await;
+    assert!(text.contains("60/100"), "{}", text);
+    assert!(text.contains("links in bio"), "{}", text);
+    let deleted = bot.api.wait_for("deleteMessage").await;
+    assert_eq!(deleted.params["message_id"], replied_id);
+    assert!(!bot.api.was_called("restrictChatMember"));
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn mutes_user_reaching_max_points() {
+    let mut bot = TestBot::start(CONFIG).await;
+
+    bot.send_reply(CHAT_ID, OWNER_ID, USER_ID, "/warn ad");
+    bot.api.wait_for_message(CHAT_ID).await;
+    bot.send_reply(CHAT_ID, OWNER_ID, USER_ID, "/warn ad");
+    let restricted = bot.api.wait_for("restrictChatMember").await;
+    assert_eq!(restricted.params["chat_id"], CHAT_ID);
+    assert_eq!(restricted.params["user_id"], USER_ID);
+    let text = bot.api.wait_for_message(CHAT_ID).await;
+    assert!(text.contains("muted"), "{}", text);
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn bans_channel_reaching_max_points() {
+    let mut bot = TestBot::start(CONFIG).await;
+
+    bot.send_reply_to_channel(OWNER_ID, "/warn ad");
+    bot.api.wait_for_message(CHAT_ID).await;
+    bot.send_reply_to_channel(OWNER_ID, "/warn ad");
+    let banned = bot.api.wait_for("banChatSenderChat").await;
+    assert_eq!(banned.params["chat_id"], CHAT_ID);
+    assert_eq!(banned.params["sender_chat_id"], CHANNEL_ID);
+    let text = bot.api.wait_for_message(CHAT_ID).await;
+    assert!(text.contains("banned"), "{}", text);
+    assert!(!bot.api.was_called("restrictChatMember"));
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn retries_punishment_after_flood_limit() {
+    let mut bot = TestBot::start(CONFIG).await;
+    bot.api.respond_next("restrictChatMember", retry_after(1));
+
+    bot.send_reply(CHAT_ID, OWNER_ID, USER_ID, "/warn ad");
+    bot.api.wait_for_message(CHAT_ID).await;
+    bot.send_reply(CHAT_ID, OWNER_ID, USER_ID, "/warn ad");
+    bot.api.wait_for("restrictChatMember").await;
+    bot.api.wait_for("restrictChatMember").await;
+    let text = bot.api.wait_for_message(CHAT_ID).await;
+    assert!(text.contains("muted"), "{}", text);
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn ignores_warns_from_members() {
+    let mut bot = TestBot::start(CONFIG).await;
+
+    bot.send_reply(CHAT_ID, USER_ID, OWNER_ID, "/warn ad");
+    // The owner's warn is handled after the member's one.
+    bot.send_reply(CHAT_ID, OWNER_ID, USER_ID, "/warn ad");
+    bot.api.wait_for("deleteMessage").await;
+    let messages = bot.api.calls().into_iter().filter(|call| call.is("sendMessage")).count();
+    assert_eq!(messages, 1);
+
+    bot.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB in AUTOWARN_TEST_MONGO"]
+async fn owner_confirms_warn_from_anonymous_admin() {
+    let mut bot = TestBot::start(CONFIG).await;
+
+    bot.send_anonymous_reply(USER_ID, "/warn ad");
+    let confirmation = bot.api.wait_for("sendMessage").await;
+    let data = confirmation.params["reply_markup"]["inline_keyboard"][0][0]["callback_data"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Only the owner can confirm.
+    bot.press_button(USER_ID, &confirmation, &data);
+    let answer = bot.api.wait_for("answerCallbackQuery").await;
+    assert_eq!(answer.params["show_alert"], true);
+    bot.press_button(OWNER_ID, &confirmation, &data);
+    bot.api.wait_for("answerCallbackQuery").await;
+    bot.api.wait_for("editMessageText").await;
+    let text = bot.api.wait_for_message(CHAT_ID).await;
+    assert!(text.contains("60/100"), "{}", text);
+
+    bot.stop().await;
+}